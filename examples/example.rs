@@ -52,7 +52,8 @@ mod scripts {
     pub fn startup(world: &mut World) {
         let system = world
             .resource::<VMModuleIndex>()
-            .get_system_id("main", "startup");
+            .get_system_id("main", "startup")
+            .unwrap();
 
         world.run_system(system).unwrap();
     }
@@ -60,7 +61,8 @@ mod scripts {
     pub fn update(world: &mut World) {
         let system = world
             .resource::<VMModuleIndex>()
-            .get_system_id("main", "update");
+            .get_system_id("main", "update")
+            .unwrap();
 
         world.run_system(system).unwrap();
     }
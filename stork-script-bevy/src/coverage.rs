@@ -0,0 +1,138 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    sync::Mutex,
+};
+
+use stork_script_core::{
+    hir::{Expr, GlobalIdx, Node},
+    module_index::{ModuleCollection, ModuleID},
+};
+
+/// Opt-in record of executed HIR nodes, stored in
+/// [`VMModuleIndex::coverage`](crate::vm_module_index::VMModuleIndex::coverage).
+#[derive(Default)]
+pub struct Coverage {
+    hits: Mutex<HashMap<GlobalIdx, u64>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleCoverage {
+    pub module: ModuleID,
+    pub path: String,
+    /// Zero-indexed line to execution count of the statements and systems
+    /// starting on it, including those that never ran.
+    pub lines: BTreeMap<usize, u64>,
+    pub systems: Vec<SystemCoverage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemCoverage {
+    pub name: String,
+    /// Zero-indexed
+    pub line: usize,
+    pub count: u64,
+}
+
+impl Coverage {
+    pub fn hits(&self) -> HashMap<GlobalIdx, u64> {
+        self.hits.lock().unwrap().clone()
+    }
+
+    pub fn reset(&self) {
+        self.hits.lock().unwrap().clear();
+    }
+
+    pub(crate) fn merge(&self, hits: HashMap<GlobalIdx, u64>) {
+        let mut all = self.hits.lock().unwrap();
+        for (node, count) in hits {
+            *all.entry(node).or_default() += count;
+        }
+    }
+
+    /// Maps the hits to lines of every module that has source code.
+    pub fn report(&self, modules: &ModuleCollection) -> Vec<ModuleCoverage> {
+        let hits = self.hits.lock().unwrap();
+        modules
+            .all_ids()
+            .filter(|module_id| !modules.get_ref(*module_id).source.text().is_empty())
+            .map(|module_id| {
+                let module = modules.get_ref(module_id);
+                let mut coverage = ModuleCoverage {
+                    module: module_id,
+                    path: modules.id_to_path(module_id).to_string(),
+                    lines: BTreeMap::new(),
+                    systems: Vec::new(),
+                };
+
+                for (idx, node) in module.nodes.iter() {
+                    let count = hits.get(&(module_id, idx).into()).copied().unwrap_or(0);
+                    match node {
+                        Node::System(system) => {
+                            let Some((line, _)) = module.line_col(idx) else {
+                                continue;
+                            };
+                            coverage.systems.push(SystemCoverage {
+                                name: system
+                                    .ident
+                                    .clone()
+                                    .unwrap_or_else(|| format!("<anonymous {}>", line + 1)),
+                                line,
+                                count,
+                            });
+                            coverage.add(line, count);
+                        }
+                        Node::Expr(Expr::Block(statements)) => {
+                            for statement in statements {
+                                let Some((line, _)) = module.line_col(*statement) else {
+                                    continue;
+                                };
+                                let count = hits
+                                    .get(&(module_id, statement).into())
+                                    .copied()
+                                    .unwrap_or(0);
+                                coverage.add(line, count);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                coverage.systems.sort_by_key(|system| system.line);
+
+                coverage
+            })
+            .collect()
+    }
+}
+
+impl ModuleCoverage {
+    /// Lines are counted as often as the statement on them that ran the most.
+    fn add(&mut self, line: usize, count: u64) {
+        let entry = self.lines.entry(line).or_default();
+        *entry = (*entry).max(count);
+    }
+
+    /// A single lcov record, `source_file` is written as `SF`.
+    pub fn to_lcov(&self, source_file: &str) -> String {
+        let mut lcov = String::new();
+        writeln!(lcov, "TN:").unwrap();
+        writeln!(lcov, "SF:{source_file}").unwrap();
+        for system in &self.systems {
+            writeln!(lcov, "FN:{},{}", system.line + 1, system.name).unwrap();
+        }
+        for system in &self.systems {
+            writeln!(lcov, "FNDA:{},{}", system.count, system.name).unwrap();
+        }
+        writeln!(lcov, "FNF:{}", self.systems.len()).unwrap();
+        let hit = self.systems.iter().filter(|s| s.count > 0).count();
+        writeln!(lcov, "FNH:{hit}").unwrap();
+        for (line, count) in &self.lines {
+            writeln!(lcov, "DA:{},{count}", line + 1).unwrap();
+        }
+        writeln!(lcov, "LF:{}", self.lines.len()).unwrap();
+        let hit = self.lines.values().filter(|count| **count > 0).count();
+        writeln!(lcov, "LH:{hit}").unwrap();
+        writeln!(lcov, "end_of_record").unwrap();
+        lcov
+    }
+}
@@ -0,0 +1,262 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex, RwLock,
+    },
+};
+
+use bevy_reflect::{PartialReflect, ReflectRef};
+use stork_script_core::module_index::ModuleID;
+
+/// Runtime side of a debugging session, stored in
+/// [`VMModuleIndex::debugger`](crate::vm_module_index::VMModuleIndex::debugger).
+///
+/// The tree walker calls it before every statement. When it decides to stop it
+/// sends a [`DebugEvent::Stopped`] with a snapshot of the stack and blocks the
+/// system until the [`DebuggerHandle`] resumes it.
+pub struct Debugger {
+    shared: Arc<Shared>,
+    commands: Mutex<Receiver<DebugCommand>>,
+    events: Sender<DebugEvent>,
+}
+
+/// Client side of a debugging session, used by e.g. a DAP server.
+pub struct DebuggerHandle {
+    shared: Arc<Shared>,
+    commands: Sender<DebugCommand>,
+    events: Mutex<Receiver<DebugEvent>>,
+}
+
+#[derive(Default)]
+struct Shared {
+    breakpoints: RwLock<HashMap<ModuleID, HashSet<usize>>>,
+    mode: Mutex<StepMode>,
+    stopped: AtomicBool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+enum StepMode {
+    #[default]
+    Run,
+    Pause,
+    In,
+    Over(usize),
+    Out(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugCommand {
+    Continue,
+    StepIn,
+    StepOver,
+    StepOut,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Breakpoint,
+    Step,
+    Pause,
+}
+
+#[derive(Debug, Clone)]
+pub enum DebugEvent {
+    Stopped {
+        reason: StopReason,
+        frames: Vec<StackFrame>,
+    },
+}
+
+/// A system or a single iteration of a query, innermost first.
+#[derive(Debug, Clone)]
+pub struct StackFrame {
+    pub name: String,
+    pub module: ModuleID,
+    /// Zero-indexed line
+    pub line: usize,
+    /// Zero-indexed byte column
+    pub column: usize,
+    pub scopes: Vec<Scope>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scope {
+    pub name: String,
+    pub variables: Vec<Variable>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Variable {
+    pub name: String,
+    pub value: String,
+    pub r#type: String,
+    pub children: Vec<Variable>,
+}
+
+impl Variable {
+    pub fn from_reflect(name: impl Into<String>, value: &dyn PartialReflect) -> Self {
+        let children = match value.reflect_ref() {
+            ReflectRef::Struct(s) => (0..s.field_len())
+                .filter_map(|i| Some(Self::from_reflect(s.name_at(i)?, s.field_at(i)?)))
+                .collect(),
+            ReflectRef::TupleStruct(s) => s
+                .iter_fields()
+                .enumerate()
+                .map(|(i, field)| Self::from_reflect(i.to_string(), field))
+                .collect(),
+            ReflectRef::Tuple(t) => t
+                .iter_fields()
+                .enumerate()
+                .map(|(i, field)| Self::from_reflect(i.to_string(), field))
+                .collect(),
+            ReflectRef::List(l) => l
+                .iter()
+                .enumerate()
+                .map(|(i, item)| Self::from_reflect(i.to_string(), item))
+                .collect(),
+            ReflectRef::Array(a) => a
+                .iter()
+                .enumerate()
+                .map(|(i, item)| Self::from_reflect(i.to_string(), item))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let r#type = value
+            .get_represented_type_info()
+            .map_or("struct", |info| info.type_path_table().short_path())
+            .to_string();
+        let value = if children.is_empty() {
+            format!("{value:?}")
+        } else {
+            format!("{type} {{ .. }}")
+        };
+        Self {
+            name: name.into(),
+            value,
+            r#type,
+            children,
+        }
+    }
+
+    pub fn opaque(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            r#type: String::new(),
+            children: Vec::new(),
+        }
+    }
+}
+
+impl Debugger {
+    pub fn new() -> (Debugger, DebuggerHandle) {
+        let shared = Arc::new(Shared::default());
+        let (commands_sender, commands) = mpsc::channel();
+        let (events, events_receiver) = mpsc::channel();
+        (
+            Debugger {
+                shared: shared.clone(),
+                commands: Mutex::new(commands),
+                events,
+            },
+            DebuggerHandle {
+                shared,
+                commands: commands_sender,
+                events: Mutex::new(events_receiver),
+            },
+        )
+    }
+
+    /// Called before each statement. `depth` is the number of blocks the
+    /// statement is nested in, `frames` is only called when stopping.
+    pub(crate) fn statement(
+        &self,
+        module: ModuleID,
+        line: usize,
+        depth: usize,
+        frames: impl FnOnce() -> Vec<StackFrame>,
+    ) {
+        let mode = *self.shared.mode.lock().unwrap();
+        let reason = match mode {
+            StepMode::Pause => Some(StopReason::Pause),
+            StepMode::In => Some(StopReason::Step),
+            StepMode::Over(d) if depth <= d => Some(StopReason::Step),
+            StepMode::Out(d) if depth < d => Some(StopReason::Step),
+            StepMode::Run | StepMode::Over(_) | StepMode::Out(_) => None,
+        };
+        let reason = reason.or_else(|| {
+            self.shared
+                .breakpoints
+                .read()
+                .unwrap()
+                .get(&module)
+                .is_some_and(|lines| lines.contains(&line))
+                .then_some(StopReason::Breakpoint)
+        });
+        let Some(reason) = reason else {
+            return;
+        };
+
+        let frames = frames();
+        self.shared.stopped.store(true, Ordering::SeqCst);
+        let command = if self
+            .events
+            .send(DebugEvent::Stopped { reason, frames })
+            .is_ok()
+        {
+            self.commands
+                .lock()
+                .unwrap()
+                .recv()
+                .unwrap_or(DebugCommand::Continue)
+        } else {
+            // Nobody is listening anymore
+            DebugCommand::Continue
+        };
+        self.shared.stopped.store(false, Ordering::SeqCst);
+        *self.shared.mode.lock().unwrap() = match command {
+            DebugCommand::Continue => StepMode::Run,
+            DebugCommand::StepIn => StepMode::In,
+            DebugCommand::StepOver => StepMode::Over(depth),
+            DebugCommand::StepOut => StepMode::Out(depth),
+        };
+    }
+}
+
+impl DebuggerHandle {
+    /// Replaces all breakpoints in a module. Lines are zero-indexed.
+    pub fn set_breakpoints(&self, module: ModuleID, lines: impl IntoIterator<Item = usize>) {
+        self.shared
+            .breakpoints
+            .write()
+            .unwrap()
+            .insert(module, lines.into_iter().collect());
+    }
+
+    pub fn clear_breakpoints(&self) {
+        self.shared.breakpoints.write().unwrap().clear();
+    }
+
+    /// Resumes a stopped system. Does nothing if no system is stopped.
+    pub fn resume(&self, command: DebugCommand) {
+        if self.shared.stopped.load(Ordering::SeqCst) {
+            let _ = self.commands.send(command);
+        }
+    }
+
+    /// Blocks until the next event. Returns `None` once the [`Debugger`] is dropped.
+    pub fn next_event(&self) -> Option<DebugEvent> {
+        self.events.lock().unwrap().recv().ok()
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.shared.stopped.load(Ordering::SeqCst)
+    }
+
+    /// Stops at the next statement that runs.
+    pub fn pause(&self) {
+        *self.shared.mode.lock().unwrap() = StepMode::Pause;
+    }
+}
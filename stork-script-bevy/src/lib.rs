@@ -2,10 +2,14 @@ use std::any::TypeId;
 
 use bevy_reflect::func::DynamicFunction;
 
+pub mod coverage;
+pub mod debugger;
 #[path = "passes/passes.rs"]
 mod passes;
+pub mod profiler;
 pub mod stork_std;
 pub mod stork_value;
+pub mod testing;
 mod utils;
 pub mod vm_module_index;
 
@@ -14,13 +18,21 @@ pub use stork_value::*;
 enum BevyBuiltinData {
     TypeId(TypeId),
     Function(DynamicFunction<'static>),
+    /// Functions that need access to the VM itself
+    Intrinsic(Intrinsic),
 }
 
-impl BevyBuiltinData {
-    pub fn unwrap_as_function(&self) -> &DynamicFunction<'static> {
+#[derive(Debug, Clone, Copy)]
+enum Intrinsic {
+    Assert,
+    Spawn,
+}
+
+impl Intrinsic {
+    fn param_names(self) -> &'static [&'static str] {
         match self {
-            BevyBuiltinData::TypeId(_) => panic!(),
-            BevyBuiltinData::Function(function) => function,
+            Intrinsic::Assert => &["condition", "message"],
+            Intrinsic::Spawn => &[],
         }
     }
 }
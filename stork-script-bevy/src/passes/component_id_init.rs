@@ -39,7 +39,10 @@ impl WorldInitCtx<'_, '_, '_> {
     fn node(&mut self, node: impl Into<GlobalIdx>) {
         let node = node.into();
         match self.modules.get_node(node) {
-            Node::System(_) => {}
+            Node::System(_) => {
+                // So it can be called like a function
+                self.variables.set(node, node.destruct());
+            }
             Node::Resource(_) => {
                 let id = self
                     .world
@@ -74,12 +77,16 @@ impl WorldInitCtx<'_, '_, '_> {
                             }
                         });
                 }
-                BevyBuiltinData::Function(_) => {
+                BevyBuiltinData::Function(_) | BevyBuiltinData::Intrinsic(_) => {
                     self.variables.set(node, node.destruct());
                 }
             },
 
-            Node::TypeIdent(_) | Node::Struct(_) | Node::Expr(_) | Node::Import(_) => {}
+            Node::Test(_)
+            | Node::TypeIdent(_)
+            | Node::Struct(_)
+            | Node::Expr(_)
+            | Node::Import(_) => {}
         }
     }
 }
@@ -17,7 +17,7 @@ use bevy_ecs::system::{DynParamBuilder, ParamBuilder, ParamSetBuilder, QueryPara
 use bevy_ecs::{prelude::SystemParamBuilder, world::World};
 
 pub fn run(
-    cache: &mut Cache,
+    cache: &Cache,
     vm_cache: &mut VMCache,
     modules: &ModuleCollection,
     module_id: usize,
@@ -73,6 +73,12 @@ impl WorldInitCtx<'_, '_, '_> {
                 let id = self.world.register_system(system);
                 self.systems.set(node, id);
             }
+            Node::Test(test) => {
+                // Tests aren't registered as systems, but their queries still need states
+                self.current_system_params = Some(Vec::new());
+                self.node((id, test.block));
+                self.current_system_params = None;
+            }
             Node::Expr(expr) => self.expr(expr, node),
             Node::TypeIdent(_)
             | Node::Struct(_)
@@ -118,7 +124,7 @@ impl WorldInitCtx<'_, '_, '_> {
 
                 self.node((id, block));
             }
-            Expr::Number(_) | Expr::Poison => {}
+            Expr::Number(_) | Expr::String(_) | Expr::Poison => {}
             Expr::ComponentAccess { entity, component } => {
                 self.node((id, entity));
                 self.node((id, component));
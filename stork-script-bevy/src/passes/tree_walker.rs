@@ -1,8 +1,12 @@
-use std::any::TypeId;
+use std::{any::TypeId, collections::HashMap};
 
 use crate::{
+    coverage::Coverage,
+    debugger::{Debugger, Scope, StackFrame, Variable},
+    profiler::{ProfileRecorder, ProfileScope, Profiler},
+    testing::AssertionFailed,
     vm_module_index::{ComponentIdMap, QueryStateMap, VMCache, VariableMap},
-    BevyBuiltinData, StorkValue,
+    BevyBuiltinData, Intrinsic, StorkValue,
 };
 use bevy_reflect::DynamicStruct;
 use itertools::Itertools;
@@ -19,9 +23,17 @@ use bevy_ecs::{
     entity::Entity,
     ptr::OwningPtr,
     reflect::{AppTypeRegistry, ReflectComponent, ReflectResource},
-    world::unsafe_world_cell::UnsafeWorldCell,
+    world::{unsafe_world_cell::UnsafeWorldCell, World},
 };
-use bevy_reflect::{func::ArgList, TypeRegistryArc};
+use bevy_reflect::{func::ArgList, ReflectFromPtr, TypeRegistryArc};
+
+/// Optional tools observing the execution
+#[derive(Default, Clone, Copy)]
+pub struct Instrumentation<'a> {
+    pub debugger: Option<&'a Debugger>,
+    pub profiler: Option<&'a Profiler>,
+    pub coverage: Option<&'a Coverage>,
+}
 
 pub fn run(
     cache: &Cache,
@@ -29,6 +41,46 @@ pub fn run(
     modules: &ModuleCollection,
     system_id: GlobalIdx,
     world: UnsafeWorldCell,
+    instrumentation: Instrumentation,
+) {
+    assert!(
+        matches!(modules.get_node(system_id), Node::System(_)),
+        "{system_id:?} isn't a system"
+    );
+    run_node(cache, vm_cache, modules, system_id, world, instrumentation);
+}
+
+/// Tests can spawn entities and call systems, which needs exclusive access to
+/// the world.
+pub fn run_test(
+    cache: &Cache,
+    vm_cache: &VMCache,
+    modules: &ModuleCollection,
+    test: GlobalIdx,
+    world: &mut World,
+    instrumentation: Instrumentation,
+) {
+    assert!(
+        matches!(modules.get_node(test), Node::Test(_)),
+        "{test:?} isn't a test"
+    );
+    run_node(
+        cache,
+        vm_cache,
+        modules,
+        test,
+        world.as_unsafe_world_cell(),
+        instrumentation,
+    );
+}
+
+fn run_node(
+    cache: &Cache,
+    vm_cache: &VMCache,
+    modules: &ModuleCollection,
+    system_id: GlobalIdx,
+    world: UnsafeWorldCell,
+    instrumentation: Instrumentation,
 ) {
     let mut vm = VM {
         modules,
@@ -41,10 +93,25 @@ pub fn run(
             .0
             .clone(),
         world,
+        debugger: instrumentation.debugger,
+        profiler: instrumentation.profiler.map(ProfileRecorder::new),
+        coverage: instrumentation
+            .coverage
+            .map(|coverage| (coverage, HashMap::new())),
+        depth: 0,
+        frames: Vec::new(),
+        locals: Vec::new(),
     };
 
     // TODO: this needs to be always done in exclusive system for structural changes
     vm.node(system_id);
+
+    if let Some(profiler) = vm.profiler {
+        profiler.finish();
+    }
+    if let Some((coverage, hits)) = vm.coverage {
+        coverage.merge(hits);
+    }
 }
 
 struct VM<'a, 'e, 'w> {
@@ -55,14 +122,51 @@ struct VM<'a, 'e, 'w> {
     query_states: &'e QueryStateMap,
     registry: TypeRegistryArc,
     world: UnsafeWorldCell<'w>,
+    debugger: Option<&'a Debugger>,
+    profiler: Option<ProfileRecorder<'a>>,
+    /// Hits are merged into the [`Coverage`] at the end of the system
+    coverage: Option<(&'a Coverage, HashMap<GlobalIdx, u64>)>,
+    /// Number of blocks we are currently in
+    depth: usize,
+    /// Systems and query iterations we are currently in, used by the debugger
+    frames: Vec<Frame>,
+    /// Definitions of variables that are currently in scope, used by the debugger
+    locals: Vec<GlobalIdx>,
+}
+
+struct Frame {
+    node: GlobalIdx,
+    locals_start: usize,
 }
 
 impl<'w> VM<'_, '_, 'w> {
     fn node(&mut self, node: impl Into<GlobalIdx>) -> StorkValue {
         let node = node.into();
         let id = node.module();
+        if let Some((_, hits)) = &mut self.coverage {
+            *hits.entry(node).or_default() += 1;
+        }
         match self.modules.get_node(node) {
-            Node::System(system) => self.node((id, system.block)),
+            Node::System(system) => {
+                self.frames.push(Frame {
+                    node,
+                    locals_start: self.locals.len(),
+                });
+                self.profile_enter(ProfileScope::System(node));
+                let ret = self.node((id, system.block));
+                self.profile_exit(ProfileScope::System(node));
+                self.frames.pop();
+                ret
+            }
+            Node::Test(test) => {
+                self.frames.push(Frame {
+                    node,
+                    locals_start: self.locals.len(),
+                });
+                self.node((id, test.block));
+                self.frames.pop();
+                ().into()
+            }
             Node::Resource(_)
             | Node::Component(_)
             | Node::TypeIdent(_)
@@ -79,10 +183,20 @@ impl<'w> VM<'_, '_, 'w> {
         let id = node.module();
         match &expr {
             Expr::Block(exprs) => {
+                self.depth += 1;
+                let locals = self.locals.len();
                 let mut ret = ().into();
                 for expr in exprs {
-                    ret = self.node((id, expr));
+                    let expr = (id, expr).into();
+                    self.statement(expr);
+                    let line = self.profile_line(expr);
+                    ret = self.node(expr);
+                    if let Some(line) = line {
+                        self.profile_exit(line);
+                    }
                 }
+                self.locals.truncate(locals);
+                self.depth -= 1;
                 ret
             }
             Expr::Identifier(_) => self
@@ -90,6 +204,7 @@ impl<'w> VM<'_, '_, 'w> {
                 .get(self.names.get(node).unwrap().definition())
                 .unwrap(),
             Expr::Number(num) => (*num).into(),
+            Expr::String(string) => string.clone().into(),
             Expr::FunctionCall { function, args } => {
                 let f = self.node((id, function)).as_::<(usize, u32)>().unwrap();
                 let f = GlobalIdx::construct(f);
@@ -98,10 +213,13 @@ impl<'w> VM<'_, '_, 'w> {
                     identifier, data, ..
                 } = self.modules.get_node(f)
                 {
-                    let logic = data
-                        .downcast_ref::<BevyBuiltinData>()
-                        .unwrap()
-                        .unwrap_as_function();
+                    let logic = match data.downcast_ref::<BevyBuiltinData>().unwrap() {
+                        BevyBuiltinData::Function(logic) => logic,
+                        BevyBuiltinData::Intrinsic(intrinsic) => {
+                            return self.intrinsic(*intrinsic, args, node);
+                        }
+                        BevyBuiltinData::TypeId(_) => unreachable!(),
+                    };
                     let args_values = args
                         .iter()
                         .map(|expr| {
@@ -133,10 +251,19 @@ impl<'w> VM<'_, '_, 'w> {
                 // unsound with structural changes e.g. adding a component to an entity?
                 let iter = unsafe { query.iter_unchecked(self.world) };
 
+                self.profile_enter(ProfileScope::Query(node));
                 for entity in iter {
                     self.variables.set(node, entity);
+                    self.frames.push(Frame {
+                        node,
+                        locals_start: self.locals.len(),
+                    });
+                    self.locals.push(node);
                     self.node((id, block));
+                    self.locals
+                        .truncate(self.frames.pop().unwrap().locals_start);
                 }
+                self.profile_exit(ProfileScope::Query(node));
 
                 ().into()
             }
@@ -230,6 +357,7 @@ impl<'w> VM<'_, '_, 'w> {
                         let expr = self.node((id, expr));
                         let expr = expr.clone_value();
                         self.variables.set((id, lvalue), StorkValue::from_box(expr));
+                        self.locals.push((id, lvalue).into());
                     }
                     Expr::ComponentAccess { entity, component } => {
                         let entity = self.node((id, entity));
@@ -357,6 +485,26 @@ impl<'w> VM<'_, '_, 'w> {
         }
     }
 
+    fn intrinsic(&mut self, intrinsic: Intrinsic, args: &[Idx], node: GlobalIdx) -> StorkValue {
+        let id = node.module();
+        match intrinsic {
+            Intrinsic::Assert => {
+                if !self.node_truthy((id, args[0])) {
+                    let message = self.node((id, args[1])).as_::<String>().unwrap();
+                    // Not a panic, so that the panic hook doesn't print it. Only
+                    // tests can assert, and `run_tests` catches it
+                    std::panic::resume_unwind(Box::new(AssertionFailed { message, node }));
+                }
+                ().into()
+            }
+            Intrinsic::Spawn => {
+                // SAFETY: only tests can spawn, and `run_test` has exclusive access to the world
+                let world = unsafe { self.world.world_mut() };
+                world.spawn_empty().id().into()
+            }
+        }
+    }
+
     fn drill_into_member_base(&self, idx: impl Into<GlobalIdx>) -> (Idx, Vec<String>) {
         let idx = idx.into();
         let module_id = idx.module();
@@ -409,6 +557,174 @@ impl<'w> VM<'_, '_, 'w> {
     }
 }
 
+impl VM<'_, '_, '_> {
+    fn statement(&self, node: GlobalIdx) {
+        let Some(debugger) = self.debugger else {
+            return;
+        };
+        let Some((line, _)) = self.modules.get_ref(node.module()).line_col(node.idx()) else {
+            return;
+        };
+        debugger.statement(node.module(), line, self.depth, || self.stack_frames(node));
+    }
+
+    fn profile_enter(&mut self, scope: ProfileScope) {
+        if self.profiler.is_none() {
+            return;
+        }
+        let name = match scope {
+            ProfileScope::System(node) => match self.modules.get_node(node) {
+                Node::System(system) => {
+                    format!("sys {}", system.ident.as_deref().unwrap_or("<anonymous>"))
+                }
+                _ => unreachable!(),
+            },
+            ProfileScope::Query(node) => match self.modules.get_node(node) {
+                Node::Expr(Expr::Query { entity, .. }) => {
+                    let (line, _) = self
+                        .modules
+                        .get_ref(node.module())
+                        .line_col(node.idx())
+                        .unwrap_or_default();
+                    format!(
+                        "query {entity} @ {}:{}",
+                        self.modules.id_to_path(node.module()),
+                        line + 1
+                    )
+                }
+                _ => unreachable!(),
+            },
+            ProfileScope::Line(module, line) => {
+                format!("{}:{}", self.modules.id_to_path(module), line + 1)
+            }
+        };
+        self.profiler.as_mut().unwrap().enter(name);
+    }
+
+    fn profile_exit(&mut self, scope: ProfileScope) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.exit(scope);
+        }
+    }
+
+    fn profile_line(&mut self, node: GlobalIdx) -> Option<ProfileScope> {
+        self.profiler.as_ref()?;
+        let (line, _) = self.modules.get_ref(node.module()).line_col(node.idx())?;
+        let scope = ProfileScope::Line(node.module(), line);
+        self.profile_enter(scope);
+        Some(scope)
+    }
+
+    fn stack_frames(&self, current: GlobalIdx) -> Vec<StackFrame> {
+        let mut position = current;
+        let mut locals_end = self.locals.len();
+        let mut frames = Vec::new();
+        for frame in self.frames.iter().rev() {
+            let (line, column) = self
+                .modules
+                .get_ref(position.module())
+                .line_col(position.idx())
+                .unwrap_or_default();
+            let mut scopes = vec![Scope {
+                name: "Locals".to_string(),
+                variables: self.locals[frame.locals_start..locals_end]
+                    .iter()
+                    .filter_map(|local| self.local_variable(*local))
+                    .collect(),
+            }];
+            let name = match self.modules.get_node(frame.node) {
+                Node::System(system) => {
+                    format!("sys {}", system.ident.as_deref().unwrap_or("<anonymous>"))
+                }
+                Node::Expr(Expr::Query { entity, .. }) => {
+                    scopes.push(Scope {
+                        name: "Entity".to_string(),
+                        variables: self.entity_components(frame.node),
+                    });
+                    format!("query {entity}")
+                }
+                Node::Test(test) => format!("test {:?}", test.name),
+                _ => unreachable!(),
+            };
+            frames.push(StackFrame {
+                name,
+                module: position.module(),
+                line,
+                column,
+                scopes,
+            });
+            position = frame.node;
+            locals_end = frame.locals_start;
+        }
+        frames
+    }
+
+    fn local_variable(&self, definition: GlobalIdx) -> Option<Variable> {
+        let name = match self.modules.get_node(definition) {
+            Node::Expr(Expr::Query { entity, .. }) => entity.clone(),
+            Node::Expr(Expr::Identifier(Identifier::Name(name))) => name.clone(),
+            _ => return None,
+        };
+        let value = self.variables.get_ref(definition)?;
+        Some(Variable::from_reflect(name, value.as_ref()))
+    }
+
+    fn entity_components(&self, query: GlobalIdx) -> Vec<Variable> {
+        let Some(entity) = self
+            .variables
+            .get_ref(query)
+            .and_then(|value| value.as_::<Entity>())
+        else {
+            return Vec::new();
+        };
+        let Some(cell) = self.world.get_entity(entity) else {
+            return Vec::new();
+        };
+        let registry = self.registry.read();
+        let components = self.world.components();
+
+        cell.archetype()
+            .components()
+            .map(|component_id| {
+                let type_id = components
+                    .get_info(component_id)
+                    .and_then(|info| info.type_id());
+                let name = self
+                    .component_ids
+                    .iter()
+                    .find(|(_, id)| **id == component_id)
+                    .and_then(|(definition, _)| match self.modules.get_node(*definition) {
+                        Node::Component(typed_ident) => Some(typed_ident.ident.clone()),
+                        Node::Builtin {
+                            identifier: Identifier::Name(name),
+                            ..
+                        } => Some(name.clone()),
+                        _ => None,
+                    })
+                    .or_else(|| {
+                        registry
+                            .get(type_id?)
+                            .map(|r| r.type_info().type_path_table().short_path().to_string())
+                    })
+                    .or_else(|| Some(components.get_info(component_id)?.name().to_string()))
+                    .unwrap_or_else(|| format!("{component_id:?}"));
+
+                let reflected = type_id.is_some_and(|type_id| {
+                    type_id == TypeId::of::<StorkValue>()
+                        || registry.get_type_data::<ReflectFromPtr>(type_id).is_some()
+                });
+                if reflected {
+                    let value =
+                        StorkValue::from_component(entity, component_id, self.world, &registry);
+                    Variable::from_reflect(name, value.as_ref())
+                } else {
+                    Variable::opaque(name, "<not reflected>")
+                }
+            })
+            .collect()
+    }
+}
+
 pub fn component_id_to_type_id(components: &Components, component_id: ComponentId) -> TypeId {
     components
         .get_info(component_id)
@@ -0,0 +1,220 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Write,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use itertools::Itertools;
+use serde_json::json;
+use stork_script_core::{hir::GlobalIdx, module_index::ModuleID};
+
+/// How many [`TraceEvent`]s a profile keeps, older ones are dropped first.
+pub const MAX_TRACE_EVENTS: usize = 100_000;
+
+/// Opt-in profiler stored in
+/// [`VMModuleIndex::profiler`](crate::vm_module_index::VMModuleIndex::profiler).
+pub struct Profiler {
+    start: Instant,
+    data: Mutex<ProfileData>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            data: Default::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProfileData {
+    pub systems: HashMap<GlobalIdx, Timing>,
+    pub queries: HashMap<GlobalIdx, Timing>,
+    /// Keyed by module and zero-indexed line. Statements nested in other
+    /// statements on the same line are counted twice.
+    pub lines: HashMap<(ModuleID, usize), Timing>,
+    /// Self time of every stack of systems, queries and lines.
+    pub stacks: HashMap<Vec<String>, Duration>,
+    /// The latest [`MAX_TRACE_EVENTS`] system runs and query executions.
+    pub events: VecDeque<TraceEvent>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timing {
+    pub count: u64,
+    pub total: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceEvent {
+    pub name: String,
+    pub category: &'static str,
+    /// Since the profiler was enabled
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileFormat {
+    /// `sys a;query b;main:3 42` lines with self time in microseconds, as used by flame graph tools
+    FoldedStacks,
+    /// Chrome's Trace Event Format, readable by e.g. `chrome://tracing`, Perfetto or speedscope
+    ChromeTrace,
+}
+
+impl Profiler {
+    pub fn data(&self) -> ProfileData {
+        self.data.lock().unwrap().clone()
+    }
+
+    pub fn reset(&self) {
+        *self.data.lock().unwrap() = Default::default();
+    }
+}
+
+impl ProfileData {
+    pub fn to_folded_stacks(&self) -> String {
+        let mut folded = String::new();
+        for (stack, time) in self.stacks.iter().sorted() {
+            writeln!(folded, "{} {}", stack.join(";"), time.as_micros()).unwrap();
+        }
+        folded
+    }
+
+    pub fn to_chrome_trace(&self) -> String {
+        let events = self
+            .events
+            .iter()
+            .map(|event| {
+                json!({
+                    "name": event.name,
+                    "cat": event.category,
+                    "ph": "X",
+                    "ts": event.start.as_secs_f64() * 1e6,
+                    "dur": event.duration.as_secs_f64() * 1e6,
+                    "pid": 0,
+                    "tid": 0,
+                })
+            })
+            .collect_vec();
+        json!({ "traceEvents": events }).to_string()
+    }
+
+    pub fn format(&self, format: ProfileFormat) -> String {
+        match format {
+            ProfileFormat::FoldedStacks => self.to_folded_stacks(),
+            ProfileFormat::ChromeTrace => self.to_chrome_trace(),
+        }
+    }
+
+    fn merge(&mut self, other: ProfileData) {
+        for (maps, other_maps) in [
+            (&mut self.systems, other.systems),
+            (&mut self.queries, other.queries),
+        ] {
+            for (idx, timing) in other_maps {
+                maps.entry(idx).or_default().add(timing);
+            }
+        }
+        for (line, timing) in other.lines {
+            self.lines.entry(line).or_default().add(timing);
+        }
+        for (stack, time) in other.stacks {
+            *self.stacks.entry(stack).or_default() += time;
+        }
+        self.events.extend(other.events);
+        let dropped = self.events.len().saturating_sub(MAX_TRACE_EVENTS);
+        self.events.drain(..dropped);
+    }
+}
+
+impl Timing {
+    fn add(&mut self, other: Timing) {
+        self.count += other.count;
+        self.total += other.total;
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ProfileScope {
+    System(GlobalIdx),
+    Query(GlobalIdx),
+    Line(ModuleID, usize),
+}
+
+/// Collects a single system run and merges it into the [`Profiler`] at the end,
+/// so the lock isn't taken for every statement.
+pub(crate) struct ProfileRecorder<'a> {
+    profiler: &'a Profiler,
+    data: ProfileData,
+    stack: Vec<OpenScope>,
+}
+
+struct OpenScope {
+    name: String,
+    start: Instant,
+    children: Duration,
+}
+
+impl<'a> ProfileRecorder<'a> {
+    pub fn new(profiler: &'a Profiler) -> Self {
+        Self {
+            profiler,
+            data: Default::default(),
+            stack: Vec::new(),
+        }
+    }
+
+    pub fn enter(&mut self, name: String) {
+        self.stack.push(OpenScope {
+            name,
+            start: Instant::now(),
+            children: Duration::ZERO,
+        });
+    }
+
+    pub fn exit(&mut self, scope: ProfileScope) {
+        let stack = self.stack.iter().map(|s| s.name.clone()).collect_vec();
+        let open = self.stack.pop().unwrap();
+        let elapsed = open.start.elapsed();
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children += elapsed;
+        }
+        *self.data.stacks.entry(stack).or_default() += elapsed.saturating_sub(open.children);
+
+        let timing = Timing {
+            count: 1,
+            total: elapsed,
+        };
+        let category = match scope {
+            ProfileScope::System(idx) => {
+                self.data.systems.entry(idx).or_default().add(timing);
+                "system"
+            }
+            ProfileScope::Query(idx) => {
+                self.data.queries.entry(idx).or_default().add(timing);
+                "query"
+            }
+            ProfileScope::Line(module, line) => {
+                self.data
+                    .lines
+                    .entry((module, line))
+                    .or_default()
+                    .add(timing);
+                return;
+            }
+        };
+        self.data.events.push_back(TraceEvent {
+            name: open.name,
+            category,
+            start: open.start.duration_since(self.profiler.start),
+            duration: elapsed,
+        });
+    }
+
+    pub fn finish(self) {
+        self.profiler.data.lock().unwrap().merge(self.data);
+    }
+}
@@ -3,11 +3,11 @@ use std::any::TypeId;
 use bevy_ecs::reflect::{ReflectComponent, ReflectResource};
 use bevy_reflect::func::IntoFunction;
 use bevy_reflect::{TypeInfo, TypeRegistry};
-use stork_script_core::hir::{Identifier, Node, Operator};
+use stork_script_core::hir::{BuiltinInfo, Identifier, Node, Operator};
 use stork_script_core::module_index::Module;
 use stork_script_core::passes::type_resolution::{InnerResolvedType, ResolvedType};
 
-use crate::BevyBuiltinData;
+use crate::{BevyBuiltinData, Intrinsic};
 
 pub fn new_module(type_registry: &TypeRegistry) -> Module {
     let mut module = Module {
@@ -16,6 +16,7 @@ pub fn new_module(type_registry: &TypeRegistry) -> Module {
         spans: Default::default(),
         source: String::default().into(),
         top_level: Default::default(),
+        public: Default::default(),
     };
 
     for (identifier, logic) in [
@@ -76,15 +77,53 @@ pub fn new_module(type_registry: &TypeRegistry) -> Module {
                 .collect(),
             ret: Box::new(resolve_type(info.return_info().type_id(), type_registry).unwrap()),
         };
+        let param_names = info
+            .args()
+            .iter()
+            .map(|arg| arg.name().map(str::to_string))
+            .collect();
 
         module.alloc_top_level(Node::Builtin {
             identifier,
             r#type: r#type.into(),
             effects: Default::default(),
+            info: BuiltinInfo {
+                param_names,
+                ..Default::default()
+            },
             data: Box::new(BevyBuiltinData::Function(logic)),
         });
     }
 
+    for (identifier, params, ret, intrinsic) in [
+        (
+            "assert",
+            vec![InnerResolvedType::Bool, InnerResolvedType::String],
+            InnerResolvedType::Unit,
+            Intrinsic::Assert,
+        ),
+        ("spawn", vec![], InnerResolvedType::Entity, Intrinsic::Spawn),
+    ] {
+        module.alloc_top_level(Node::Builtin {
+            identifier: identifier.into(),
+            r#type: InnerResolvedType::Function {
+                params,
+                ret: Box::new(ret),
+            }
+            .into(),
+            effects: Default::default(),
+            info: BuiltinInfo {
+                test_only: true,
+                param_names: intrinsic
+                    .param_names()
+                    .iter()
+                    .map(|name| Some(name.to_string()))
+                    .collect(),
+            },
+            data: Box::new(BevyBuiltinData::Intrinsic(intrinsic)),
+        });
+    }
+
     for registration in type_registry.iter() {
         let identifier = Identifier::Name(
             registration
@@ -95,20 +134,25 @@ pub fn new_module(type_registry: &TypeRegistry) -> Module {
         );
 
         if let Some(inner) = resolve_type_info(registration.type_info()) {
-            let component_or_resource = type_registry
+            let component = type_registry
                 .get_type_data::<ReflectComponent>(registration.type_id())
-                .is_some()
-                || type_registry
-                    .get_type_data::<ReflectResource>(registration.type_id())
-                    .is_some();
+                .is_some();
+            let resource = type_registry
+                .get_type_data::<ReflectResource>(registration.type_id())
+                .is_some();
 
             module.alloc_top_level(Node::Builtin {
                 identifier,
                 r#type: ResolvedType {
                     inner,
-                    component_or_resource,
+                    component_or_resource: component || resource,
                 },
                 effects: Default::default(),
+                info: BuiltinInfo {
+                    component,
+                    resource,
+                    ..Default::default()
+                },
                 data: Box::new(BevyBuiltinData::TypeId(registration.type_id())),
             });
         }
@@ -138,6 +182,7 @@ fn resolve_type_info(type_info: &TypeInfo) -> Option<InnerResolvedType> {
         TypeInfo::Opaque(info) if info.is::<()>() => InnerResolvedType::Unit,
         TypeInfo::Opaque(info) if info.is::<f32>() => InnerResolvedType::F32,
         TypeInfo::Opaque(info) if info.is::<bool>() => InnerResolvedType::Bool,
+        TypeInfo::Opaque(info) if info.is::<String>() => InnerResolvedType::String,
         TypeInfo::Opaque(info)
             if info.is::<u8>()
                 || info.is::<u16>()
@@ -152,8 +197,7 @@ fn resolve_type_info(type_info: &TypeInfo) -> Option<InnerResolvedType> {
                 || info.is::<i128>()
                 || info.is::<isize>()
                 || info.is::<f64>()
-                || info.is::<char>()
-                || info.is::<String>() =>
+                || info.is::<char>() =>
         {
             return None
         }
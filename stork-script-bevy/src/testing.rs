@@ -0,0 +1,85 @@
+use std::any::Any;
+
+use stork_script_core::{
+    diagnostic::Code,
+    hir::GlobalIdx,
+    module_index::ModuleCollection,
+    report::{Label, Report, ReportKind, Span},
+};
+
+/// Unwinds out of the tree walker when an `assert` fails.
+pub(crate) struct AssertionFailed {
+    pub message: String,
+    pub node: GlobalIdx,
+}
+
+#[derive(Debug, Clone)]
+pub struct TestResult {
+    pub name: String,
+    pub test: GlobalIdx,
+    pub failure: Option<TestFailure>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TestFailure {
+    pub message: String,
+    /// The failed `assert`, or the whole test if it panicked somewhere else
+    pub span: Span,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+
+    pub fn report(&self) -> Option<Report> {
+        let failure = self.failure.as_ref()?;
+        Some(
+            Report::build(
+                ReportKind::Error,
+                Code::TestFailed,
+                failure.span.0,
+                failure.span.1.start,
+            )
+            .with_message(format!("Test {:?} failed", self.name))
+            .with_label(Label::new(failure.span.clone()).with_message(&failure.message))
+            .finish(),
+        )
+    }
+}
+
+impl TestFailure {
+    pub(crate) fn from_panic(
+        payload: Box<dyn Any + Send>,
+        test: GlobalIdx,
+        modules: &ModuleCollection,
+    ) -> Self {
+        let payload = match payload.downcast::<AssertionFailed>() {
+            Ok(assertion) => {
+                return Self {
+                    message: assertion.message,
+                    span: span(modules, assertion.node),
+                }
+            }
+            Err(payload) => payload,
+        };
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Box<dyn Any>".to_string());
+        Self {
+            message: format!("panicked: {message}"),
+            span: span(modules, test),
+        }
+    }
+}
+
+fn span(modules: &ModuleCollection, node: GlobalIdx) -> Span {
+    let range = modules
+        .get_ref(node.module())
+        .spans
+        .get(node.idx())
+        .map_or_else(Default::default, |ptr| ptr.text_range().into());
+    (node.module(), range)
+}
@@ -1,20 +1,35 @@
-use std::sync::RwLock;
+use std::{
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    sync::RwLock,
+};
 
 use anyhow::bail;
 use bevy_ecs::{
     component::ComponentId,
     entity::Entity,
     query::QueryState,
+    reflect::AppTypeRegistry,
     system::{Resource, SystemId},
     world::{unsafe_world_cell::UnsafeWorldCell, World},
 };
 
 use stork_script_core::{
-    hir::{GlobalIdx, Identifier},
+    hir::{GlobalIdx, Identifier, Node},
     module_index::{cache::GlobalMap, ModuleIndex},
+    schema::Schema,
 };
+use tracing::info_span;
 
-use super::{passes, stork_std, StorkValue};
+use super::{
+    coverage::{Coverage, ModuleCoverage},
+    debugger::Debugger,
+    passes::{self, tree_walker::Instrumentation},
+    profiler::{ProfileData, ProfileFormat, Profiler},
+    stork_std,
+    testing::{TestFailure, TestResult},
+    StorkValue,
+};
 
 pub type ComponentIdMap = GlobalMap<ComponentId>;
 pub type VariableMap = GlobalMap<StorkValue>;
@@ -30,18 +45,29 @@ pub struct VMCache {
     pub systems: SystemMap,
 }
 
+impl VMCache {
+    fn init(&mut self, index: &ModuleIndex, world: &mut World) {
+        for module_id in index.modules.all_ids() {
+            passes::component_id_init::run(self, &index.modules, module_id, world);
+        }
+        for module_id in index.modules.all_ids() {
+            passes::system_init::run(&index.cache, self, &index.modules, module_id, world);
+        }
+    }
+}
+
 #[derive(Default, Resource)]
 pub struct VMModuleIndex {
     pub index: ModuleIndex,
     pub vm_cache: VMCache,
+    pub debugger: Option<Debugger>,
+    pub profiler: Option<Profiler>,
+    pub coverage: Option<Coverage>,
 }
 
 impl VMModuleIndex {
     pub fn add_std(&mut self, world: &mut World) {
-        let type_registry = &world
-            .get_resource::<bevy_ecs::prelude::AppTypeRegistry>()
-            .unwrap()
-            .read();
+        let type_registry = &world.get_resource::<AppTypeRegistry>().unwrap().read();
         self.index
             .add_module("std".to_string(), |_| {
                 Ok(stork_std::new_module(type_registry))
@@ -49,6 +75,15 @@ impl VMModuleIndex {
             .unwrap();
     }
 
+    /// Writes the builtins of `std` to `path`, so the language server and
+    /// `stork check --schema` know this game's types.
+    pub fn export_schema(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let Some(std) = self.index.modules.get_id("std") else {
+            bail!("There is no std module, add it with add_std first");
+        };
+        Schema::from_module(self.index.modules.get_ref(std)).save(path.as_ref())
+    }
+
     pub fn compile(&mut self, world: &mut World) -> anyhow::Result<()> {
         self.index.compile()?;
 
@@ -56,41 +91,163 @@ impl VMModuleIndex {
             bail!("There were errors during compilation");
         }
 
-        for module_id in self.index.modules.all_ids() {
-            passes::component_id_init::run(
-                &mut self.vm_cache,
-                &self.index.modules,
-                module_id,
-                world,
-            );
-        }
-        for module_id in self.index.modules.all_ids() {
-            passes::system_init::run(
-                &mut self.index.cache,
-                &mut self.vm_cache,
-                &self.index.modules,
-                module_id,
-                world,
-            );
-        }
+        self.vm_cache.init(&self.index, world);
 
         Ok(())
     }
 
+    /// Runs every `test` item, each in a fresh world that only shares the
+    /// [`AppTypeRegistry`] with `world`.
+    pub fn run_tests(&self, world: &World) -> Vec<TestResult> {
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let modules = &self.index.modules;
+        modules
+            .all_ids()
+            .flat_map(|module_id| modules.top_level_ids(module_id))
+            .filter_map(|test| match modules.get_node(test) {
+                Node::Test(t) => Some((test, t.name.clone())),
+                _ => None,
+            })
+            .map(|(test, name)| {
+                let mut test_world = World::new();
+                test_world.insert_resource(registry.clone());
+                let mut vm_cache = VMCache::default();
+                vm_cache.init(&self.index, &mut test_world);
+
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    passes::tree_walker::run_test(
+                        &self.index.cache,
+                        &vm_cache,
+                        modules,
+                        test,
+                        &mut test_world,
+                        self.instrumentation(),
+                    );
+                }));
+                TestResult {
+                    name,
+                    test,
+                    failure: result
+                        .err()
+                        .map(|payload| TestFailure::from_panic(payload, test, modules)),
+                }
+            })
+            .collect()
+    }
+
+    pub fn print_test_results(&self, results: &[TestResult]) {
+        for result in results {
+            let status = if result.passed() { "ok" } else { "FAILED" };
+            println!("test {} ... {status}", result.name);
+        }
+        for report in results.iter().filter_map(TestResult::report) {
+            report.print(&self.index.modules).unwrap();
+        }
+        let passed = results.iter().filter(|result| result.passed()).count();
+        println!(
+            "test result: {passed} passed; {} failed",
+            results.len() - passed
+        );
+    }
+
+    /// Runs the system in a tracing span. Span names have to be known at
+    /// compile time, so every span is named `script system` and the system
+    /// is in its `name` and `module` fields. Profiles name their events after
+    /// the system instead, see [`ProfileData::to_chrome_trace`].
     pub fn run_system(&self, system_id: GlobalIdx, world: UnsafeWorldCell) {
+        let Node::System(system) = self.index.modules.get_node(system_id) else {
+            panic!("{system_id:?} isn't a system");
+        };
+        let _span = info_span!(
+            "script system",
+            name = system.ident.as_deref().unwrap_or("<anonymous>"),
+            module = self.index.modules.id_to_path(system_id.module()),
+        )
+        .entered();
+
         passes::tree_walker::run(
             &self.index.cache,
             &self.vm_cache,
             &self.index.modules,
             system_id,
             world,
+            self.instrumentation(),
         );
     }
 
-    pub fn get_system_id(&self, path: &str, name: &str) -> SystemId {
-        let module_id = self.index.modules.path_to_id(path);
-        let idx =
-            self.index.modules.top_level_names(module_id)[&Identifier::Name(name.to_string())];
-        self.vm_cache.systems.get((module_id, idx)).unwrap()
+    fn instrumentation(&self) -> Instrumentation<'_> {
+        Instrumentation {
+            debugger: self.debugger.as_ref(),
+            profiler: self.profiler.as_ref(),
+            coverage: self.coverage.as_ref(),
+        }
+    }
+
+    /// The item `name` in the module `path`, if both exist.
+    pub fn get_system_idx(&self, path: &str, name: &str) -> Option<GlobalIdx> {
+        let module_id = self.index.modules.get_id(path)?;
+        let idx = *self
+            .index
+            .modules
+            .top_level_names(module_id)
+            .get(&Identifier::Name(name.to_string()))?;
+        Some((module_id, idx).into())
+    }
+
+    /// The id to run the system `name` in the module `path` with, if there is
+    /// such a system.
+    pub fn get_system_id(&self, path: &str, name: &str) -> Option<SystemId> {
+        self.vm_cache.systems.get(self.get_system_idx(path, name)?)
+    }
+
+    /// Starts recording time spent in systems, queries and lines from now on.
+    pub fn enable_profiling(&mut self) {
+        self.profiler.get_or_insert_with(Default::default);
+    }
+
+    pub fn profile(&self) -> Option<ProfileData> {
+        self.profiler.as_ref().map(Profiler::data)
+    }
+
+    pub fn write_profile(
+        &self,
+        path: impl AsRef<Path>,
+        format: ProfileFormat,
+    ) -> anyhow::Result<()> {
+        let Some(profile) = self.profile() else {
+            bail!("Profiling isn't enabled");
+        };
+        std::fs::write(path, profile.format(format))?;
+        Ok(())
+    }
+
+    /// Starts recording which nodes run from now on.
+    pub fn enable_coverage(&mut self) {
+        self.coverage.get_or_insert_with(Default::default);
+    }
+
+    pub fn coverage(&self) -> Option<Vec<ModuleCoverage>> {
+        Some(self.coverage.as_ref()?.report(&self.index.modules))
+    }
+
+    /// Writes a single lcov file with a record for each module. The source file
+    /// of a module is assumed to be `<scripts_dir>/<module path>.strk`.
+    pub fn write_lcov(
+        &self,
+        path: impl AsRef<Path>,
+        scripts_dir: impl AsRef<Path>,
+    ) -> anyhow::Result<()> {
+        let Some(coverage) = self.coverage() else {
+            bail!("Coverage isn't enabled");
+        };
+        let lcov: String = coverage
+            .iter()
+            .map(|module| {
+                let source_file = scripts_dir.as_ref().join(format!("{}.strk", module.path));
+                module.to_lcov(&source_file.to_string_lossy())
+            })
+            .collect();
+        std::fs::write(path, lcov)?;
+        Ok(())
     }
 }
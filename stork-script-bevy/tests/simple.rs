@@ -94,10 +94,12 @@ fn simple() {
 
     let first_system = world
         .resource::<VMModuleIndex>()
-        .get_system_id("main", "first_system");
+        .get_system_id("main", "first_system")
+        .unwrap();
     let second_system = world
         .resource::<VMModuleIndex>()
-        .get_system_id("main", "second_system");
+        .get_system_id("main", "second_system")
+        .unwrap();
 
     world.run_system(first_system).unwrap();
     world.run_system(second_system).unwrap();
@@ -119,3 +121,163 @@ fn simple() {
     }
     assert_eq!(world.resource::<Difficulty>(), &Difficulty { value: 10. });
 }
+
+#[test]
+fn profiling() {
+    let mut world = run("
+    use std
+
+    sys first_system {
+        query entity {
+            entity[Transform].translation.x += 1;
+        }
+    }
+    ");
+    world.resource_mut::<VMModuleIndex>().enable_profiling();
+
+    let first_system = world
+        .resource::<VMModuleIndex>()
+        .get_system_id("main", "first_system")
+        .unwrap();
+    world.run_system(first_system).unwrap();
+    world.run_system(first_system).unwrap();
+
+    let vm = world.resource::<VMModuleIndex>();
+    let profile = vm.profile().unwrap();
+    let system = vm.get_system_idx("main", "first_system").unwrap();
+    assert_eq!(profile.systems[&system].count, 2);
+    assert_eq!(profile.queries.values().map(|t| t.count).sum::<u64>(), 2);
+    // The query statement runs once per system run, its body once per entity.
+    // Lines are zero-indexed here, but one-indexed in stack names
+    let module = vm.index.modules.get_id("main").unwrap();
+    assert_eq!(profile.lines[&(module, 4)].count, 2);
+    assert_eq!(profile.lines[&(module, 5)].count, 6);
+
+    let folded = profile.to_folded_stacks();
+    assert!(folded.contains("sys first_system;main:5;query entity @ main:5;main:6 "));
+    assert!(profile
+        .to_chrome_trace()
+        .contains("\"name\":\"sys first_system\""));
+}
+
+#[test]
+fn coverage() {
+    let mut world = run("
+    use std
+
+    sys first_system {
+        query entity {
+            entity[Transform].translation.x += 1;
+        }
+        if [Difficulty].value > 5 {
+            [Difficulty].value = 0;
+        }
+    }
+
+    sys second_system {
+        [Difficulty].value = 10;
+    }
+    ");
+    world.resource_mut::<VMModuleIndex>().enable_coverage();
+
+    let first_system = world
+        .resource::<VMModuleIndex>()
+        .get_system_id("main", "first_system")
+        .unwrap();
+    world.run_system(first_system).unwrap();
+
+    let coverage = world.resource::<VMModuleIndex>().coverage().unwrap();
+    let [main] = &coverage[..] else {
+        panic!("Only modules with source should be reported");
+    };
+    assert_eq!(main.path, "main");
+    assert_eq!(
+        main.to_lcov("scripts/main.strk"),
+        "TN:
+SF:scripts/main.strk
+FN:4,first_system
+FN:13,second_system
+FNDA:1,first_system
+FNDA:0,second_system
+FNF:2
+FNH:1
+DA:4,1
+DA:5,1
+DA:6,3
+DA:8,1
+DA:9,0
+DA:13,0
+DA:14,0
+LF:7
+LH:4
+end_of_record
+",
+    );
+}
+
+#[test]
+fn script_tests() {
+    let world = run(r#"
+    use std
+
+    comp Velocity: f32
+
+    sys move_right {
+        query entity {
+            entity[Transform].translation.x += entity[Velocity];
+        }
+    }
+
+    test "moves right" {
+        let e = spawn();
+        let e[Transform] = Transform { translation: Translation { x: 1, y: 0, z: 0 } };
+        let e[Velocity] = 2;
+        move_right();
+        move_right();
+        assert(e[Transform].translation.x == 5, "moved twice");
+    }
+
+    test "starts fresh" {
+        query entity {
+            if entity[Velocity] {
+                assert(0 == 1, "entities of other tests are gone");
+            }
+        }
+    }
+
+    test "fails" {
+        let e = spawn();
+        let e[Velocity] = 2;
+        move_right();
+        assert(e[Velocity] == 3, "velocity is 3");
+    }
+    "#);
+
+    let vm = world.resource::<VMModuleIndex>();
+    let entities = world.entities().len();
+    let results = vm.run_tests(&world);
+    let summary = results
+        .iter()
+        .map(|result| (result.name.as_str(), result.passed()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        [
+            ("moves right", true),
+            ("starts fresh", true),
+            ("fails", false)
+        ]
+    );
+
+    let failure = results[2].failure.as_ref().unwrap();
+    assert_eq!(failure.message, "velocity is 3");
+    let main = vm.index.modules.get_id("main").unwrap();
+    let source = vm.index.modules.get_ref(main).source.text();
+    assert_eq!(
+        &source[failure.span.1.clone()],
+        r#"assert(e[Velocity] == 3, "velocity is 3")"#
+    );
+
+    // The world the tests were started from is untouched
+    assert_eq!(world.entities().len(), entities);
+}
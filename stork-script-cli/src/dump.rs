@@ -0,0 +1,72 @@
+use std::{fs, path::Path, process::ExitCode};
+
+use anyhow::Context;
+use stork_script_core::{
+    ast, cst,
+    module_index::{Module, ModuleID},
+    passes::{lower, pretty_print},
+};
+
+use super::Script;
+
+/// Prints the CST, AST and HIR of every script. A broken script doesn't stop
+/// the others from being dumped, its reports are printed with everything else.
+pub fn run(scripts: &[Script], schema: Option<&Path>) -> anyhow::Result<ExitCode> {
+    let (_, mut vm) = super::load(&[], schema)?;
+    let mut module_ids = Vec::new();
+    for Script { file, module: path } in scripts {
+        let source = fs::read_to_string(file)
+            .with_context(|| format!("Couldn't read {}", file.display()))?;
+        vm.index.add_module(path.clone(), |module_id| {
+            module_ids.push((path, module_id));
+            Ok(parse(path, &source, module_id))
+        })?;
+    }
+
+    if let Err(err) = vm.index.compile() {
+        vm.index.print_errors();
+        eprintln!("{err:?}");
+        return Ok(ExitCode::FAILURE);
+    }
+    for (path, module_id) in module_ids {
+        println!("== {path} HIR ==");
+        println!(
+            "{}",
+            pretty_print::run(&mut vm.index.cache, &vm.index.modules, module_id)
+        );
+    }
+
+    vm.index.print_errors();
+    Ok(if vm.index.has_errors() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Prints the CST and AST of a script, as far as they can be built, and lowers
+/// it. A script that can't be lowered becomes a module without items that
+/// keeps every report.
+fn parse(path: &str, source: &str, module_id: ModuleID) -> Module {
+    let mut reports = Vec::new();
+    let lowered = cst::run(source, module_id).and_then(|(green, errors)| {
+        println!("== {path} CST ==");
+        println!("{:#?}", cst::SyntaxNode::new_root(green.clone()));
+        reports.clone_from(&errors);
+        let root = ast::run(green, module_id)?;
+        println!("== {path} AST ==");
+        println!("{root:#?}");
+        lower::run(root, source.to_string().into(), module_id, errors)
+    });
+    lowered.unwrap_or_else(|report| {
+        reports.push(*report);
+        Module {
+            source: source.to_string().into(),
+            nodes: Default::default(),
+            spans: Default::default(),
+            top_level: Vec::new(),
+            public: Default::default(),
+            parser_errors: reports,
+        }
+    })
+}
@@ -0,0 +1,275 @@
+//! `stork`, for working with `.strk` scripts outside of a bevy app.
+
+mod dump;
+mod run;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{bail, Context};
+use bevy_ecs::{reflect::AppTypeRegistry, world::World};
+use bevy_transform::components::{GlobalTransform, Transform};
+use clap::{Parser, Subcommand, ValueEnum};
+use stork_script_bevy::vm_module_index::VMModuleIndex;
+use stork_script_core::{
+    diagnostic::Code,
+    format,
+    module_index::{module_path, Module},
+    schema::Schema,
+};
+
+#[derive(Parser)]
+#[command(name = "stork", version, about, arg_required_else_help = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Describe an error code, like `E0004`
+    #[arg(long, value_name = "CODE", exclusive = true)]
+    explain: Option<String>,
+    /// The directory modules are named relative to, like `game::physics` for
+    /// `game/physics.strk` [default: the current directory]
+    #[arg(long, global = true, value_name = "DIR")]
+    root: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+enum MessageFormat {
+    /// Annotated source snippets
+    #[default]
+    Human,
+    /// An array of diagnostics with stable codes, on stdout
+    Json,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compile modules and report their errors, exits with 1 if there were any
+    Check {
+        /// Script files, each one is a module named after its path from `--root`
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Check against a game's exported `std` instead of the builtin types
+        #[arg(long)]
+        schema: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t)]
+        message_format: MessageFormat,
+    },
+    /// Print the syntax tree, the AST and the resolved HIR of modules
+    Dump {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        #[arg(long)]
+        schema: Option<PathBuf>,
+    },
+    /// Format modules in place, refusing ones with syntax errors
+    Fmt {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// Only list the files that aren't formatted and exit with 1 if there are any
+        #[arg(long)]
+        check: bool,
+    },
+    /// Run systems against a headless world and print the resulting scene
+    Run {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        /// A bevy scene in RON to populate the world with
+        #[arg(long)]
+        scene: Option<PathBuf>,
+        /// `module::system` or just `system`, systems run in the given order
+        #[arg(short, long = "system", required = true)]
+        systems: Vec<String>,
+        /// How many times all systems run
+        #[arg(long, default_value_t = 1)]
+        frames: usize,
+        /// Write the resulting scene here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Export the schema of the builtin `std`, as a game would with `export_schema`
+    Schema { output: PathBuf },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match (cli.explain, cli.command) {
+        (Some(code), _) => explain(&code),
+        (None, None) => Err(anyhow::anyhow!("Expected a command or --explain")),
+        (None, Some(command)) => run_command(command, cli.root.as_deref()),
+    };
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_command(command: Command, root: Option<&Path>) -> anyhow::Result<ExitCode> {
+    match command {
+        Command::Check {
+            files,
+            schema,
+            message_format,
+        } => check(&scripts(&files, root)?, schema.as_deref(), message_format),
+        Command::Dump { files, schema } => dump::run(&scripts(&files, root)?, schema.as_deref()),
+        Command::Fmt { files, check } => fmt(&scripts(&files, root)?, check),
+        Command::Run {
+            files,
+            scene,
+            systems,
+            frames,
+            output,
+        } => run::run(
+            &scripts(&files, root)?,
+            scene.as_deref(),
+            &systems,
+            frames,
+            output.as_deref(),
+        ),
+        Command::Schema { output } => load(&[], None)
+            .and_then(|(_, vm)| vm.export_schema(&output))
+            .map(|()| ExitCode::SUCCESS),
+    }
+}
+
+fn explain(code: &str) -> anyhow::Result<ExitCode> {
+    let code = Code::parse(code).with_context(|| {
+        let known = Code::ALL.map(Code::as_str).join(", ");
+        format!("{code} isn't an error code, they are {known}")
+    })?;
+    println!("{}\n\n{}", code.as_str(), code.explanation());
+    Ok(ExitCode::SUCCESS)
+}
+
+fn check(
+    scripts: &[Script],
+    schema: Option<&Path>,
+    message_format: MessageFormat,
+) -> anyhow::Result<ExitCode> {
+    let (_, mut vm) = load(scripts, schema)?;
+    vm.index.compile()?;
+    match message_format {
+        MessageFormat::Human => vm.index.print_errors(),
+        MessageFormat::Json => println!("{}", vm.index.diagnostics_json()?),
+    }
+    Ok(if vm.index.has_errors() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+fn fmt(scripts: &[Script], check: bool) -> anyhow::Result<ExitCode> {
+    let (_, vm) = load(scripts, None)?;
+    let mut code = ExitCode::SUCCESS;
+    for Script { file, module } in scripts {
+        let Some(module_id) = vm.index.modules.get_id(module) else {
+            bail!("There's no module {module}");
+        };
+        let source = vm.index.modules.get_ref(module_id).source.text();
+        let formatted = match format::run(source, module_id) {
+            Ok(formatted) => formatted,
+            Err(report) => {
+                report.print(&vm.index.modules)?;
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{}", file.display());
+            code = ExitCode::FAILURE;
+        } else {
+            fs::write(file, formatted)
+                .with_context(|| format!("Couldn't write {}", file.display()))?;
+        }
+    }
+    Ok(code)
+}
+
+/// Creates a world with the types scripts can use and adds `scripts` and `std` as modules.
+/// A `std` built from `schema` can be compiled but not run.
+fn load(scripts: &[Script], schema: Option<&Path>) -> anyhow::Result<(World, VMModuleIndex)> {
+    let mut world = World::new();
+    world.init_resource::<AppTypeRegistry>();
+    {
+        let mut registry = world.resource::<AppTypeRegistry>().write();
+        registry.register::<()>();
+        registry.register::<f32>();
+        registry.register::<bool>();
+        registry.register::<String>();
+        registry.register::<Transform>();
+        registry.register::<GlobalTransform>();
+    }
+
+    let mut vm = VMModuleIndex::default();
+    for Script { file, module } in scripts {
+        let source = fs::read_to_string(file)
+            .with_context(|| format!("Couldn't read {}", file.display()))?;
+        vm.index.add_module(module.clone(), |module_id| {
+            Module::from_source(&source, module_id)
+        })?;
+    }
+    match schema {
+        Some(schema) => {
+            let schema = Schema::load(schema)?;
+            vm.index.add_module("std", |_| Ok(schema.to_module()))?;
+        }
+        None => vm.add_std(&mut world),
+    }
+    Ok((world, vm))
+}
+
+/// A script file and the module it's loaded as.
+struct Script {
+    file: PathBuf,
+    module: String,
+}
+
+/// Names each file after its path from `root`, so the name doesn't depend on
+/// how the path was written. Two files can't be the same module.
+fn scripts(files: &[PathBuf], root: Option<&Path>) -> anyhow::Result<Vec<Script>> {
+    let root = match root {
+        Some(root) => root.to_path_buf(),
+        None => std::env::current_dir()?,
+    };
+    let root = fs::canonicalize(&root)
+        .with_context(|| format!("Couldn't find the root {}", root.display()))?;
+    let mut scripts: Vec<Script> = Vec::new();
+    for file in files {
+        let path =
+            fs::canonicalize(file).with_context(|| format!("Couldn't find {}", file.display()))?;
+        let module = module_path(&root, &path).with_context(|| {
+            format!(
+                "{} isn't in {}, pass a --root that contains it",
+                file.display(),
+                root.display()
+            )
+        })?;
+        if module == "std" {
+            bail!(
+                "{} would be the module `std`, which is builtin",
+                file.display()
+            );
+        }
+        if let Some(other) = scripts.iter().find(|script| script.module == module) {
+            bail!(
+                "{} and {} would both be the module `{module}`",
+                other.file.display(),
+                file.display()
+            );
+        }
+        scripts.push(Script {
+            file: file.clone(),
+            module,
+        });
+    }
+    Ok(scripts)
+}
@@ -0,0 +1,136 @@
+use std::{fs, path::Path, process::ExitCode};
+
+use anyhow::{anyhow, bail, Context};
+use bevy_ecs::{entity::EntityHashMap, reflect::AppTypeRegistry, world::World};
+use bevy_scene::{
+    ron,
+    serde::{SceneDeserializer, SceneSerializer},
+    DynamicScene, DynamicSceneBuilder,
+};
+use serde::de::DeserializeSeed;
+use stork_script_bevy::vm_module_index::VMModuleIndex;
+use stork_script_core::{
+    hir::{GlobalIdx, Identifier, Node},
+    module_index::ModuleCollection,
+};
+
+use super::Script;
+
+pub fn run(
+    scripts: &[Script],
+    scene: Option<&Path>,
+    systems: &[String],
+    frames: usize,
+    output: Option<&Path>,
+) -> anyhow::Result<ExitCode> {
+    let (mut world, mut vm) = super::load(scripts, None)?;
+    if let Some(scene) = scene {
+        load_scene(&mut world, scene)?;
+    }
+
+    if let Err(err) = vm.compile(&mut world) {
+        vm.index.print_errors();
+        return Err(err);
+    }
+    let files = scripts
+        .iter()
+        .map(|script| script.module.clone())
+        .collect::<Vec<_>>();
+    let systems = systems
+        .iter()
+        .map(|system| {
+            let idx = find_system(&vm.index.modules, &files, system)?;
+            Ok(vm.vm_cache.systems.get(idx).unwrap())
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    world.insert_resource(vm);
+    for _ in 0..frames {
+        for system in &systems {
+            world.run_system(*system).map_err(|err| anyhow!("{err}"))?;
+        }
+    }
+    world.remove_resource::<VMModuleIndex>();
+
+    let scene = save_scene(&world)?;
+    match output {
+        Some(output) => fs::write(output, scene)
+            .with_context(|| format!("Couldn't write {}", output.display()))?,
+        None => print!("{scene}"),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn load_scene(world: &mut World, path: &Path) -> anyhow::Result<()> {
+    let source =
+        fs::read_to_string(path).with_context(|| format!("Couldn't read {}", path.display()))?;
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let scene: DynamicScene = {
+        let registry = registry.read();
+        let mut deserializer = ron::de::Deserializer::from_str(&source)?;
+        SceneDeserializer {
+            type_registry: &registry,
+        }
+        .deserialize(&mut deserializer)
+        .map_err(|err| deserializer.span_error(err))
+        .with_context(|| format!("Couldn't load scene {}", path.display()))?
+    };
+    scene
+        .write_to_world(world, &mut EntityHashMap::default())
+        .map_err(|err| anyhow!("{err}"))
+}
+
+/// Only components and resources in the type registry are part of the scene.
+fn save_scene(world: &World) -> anyhow::Result<String> {
+    let scene = DynamicSceneBuilder::from_world(world)
+        .extract_entities(world.iter_entities().map(|entity| entity.id()))
+        .extract_resources()
+        .remove_empty_entities()
+        .build();
+    let registry = world.resource::<AppTypeRegistry>().read();
+    Ok(ron::ser::to_string_pretty(
+        &SceneSerializer::new(&scene, &registry),
+        ron::ser::PrettyConfig::default(),
+    )?)
+}
+
+/// `name` is either `module::system`, or a system in any of `files`.
+fn find_system(
+    modules: &ModuleCollection,
+    files: &[String],
+    name: &str,
+) -> anyhow::Result<GlobalIdx> {
+    let (paths, system) = match name.rsplit_once("::") {
+        Some((path, system)) => {
+            if !files.iter().any(|file| file == path) {
+                bail!("There's no module {path}");
+            }
+            (vec![path], system)
+        }
+        None => (files.iter().map(String::as_str).collect(), name),
+    };
+
+    let found: Vec<_> = paths
+        .into_iter()
+        .filter_map(|path| {
+            let module_id = modules.get_id(path)?;
+            let idx = *modules
+                .top_level_names(module_id)
+                .get(&Identifier::Name(system.to_string()))?;
+            let idx = GlobalIdx::from((module_id, idx));
+            matches!(modules.get_node(idx), Node::System(_)).then_some((path, idx))
+        })
+        .collect();
+    match &found[..] {
+        [] => bail!("There's no system {name}"),
+        [(_, idx)] => Ok(*idx),
+        [..] => bail!(
+            "{name} is ambiguous, it could be any of {}",
+            found
+                .iter()
+                .map(|(path, _)| format!("{path}::{system}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
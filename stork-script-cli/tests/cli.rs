@@ -0,0 +1,180 @@
+use std::process::{Command, Output};
+
+fn stork(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_stork"))
+        .current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn check() {
+    assert!(stork(&["check", "movement.strk"]).status.success());
+
+    let output = stork(&["check", "movement.strk", "broken.strk"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Couldn't find name"));
+
+    let output = stork(&[
+        "check",
+        "movement.strk",
+        "broken.strk",
+        "--message-format",
+        "json",
+    ]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for field in [
+        r#""code": "E0004""#,
+        r#""severity": "error""#,
+        r#""module": "broken""#,
+        r#""line": 3"#,
+    ] {
+        assert!(stdout.contains(field), "{field} missing from\n{stdout}");
+    }
+}
+
+#[test]
+fn module_paths() {
+    // `game/physics.strk` is the module `game::physics`
+    let output = stork(&["check", "velocity.strk", "game/physics.strk"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert!(!stork(&["check", "velocity.strk"]).status.success());
+    // The name doesn't depend on how the path is written
+    let output = stork(&["check", "./velocity.strk", "../fixtures/game/physics.strk"]);
+    assert!(output.status.success());
+
+    let output = stork(&["check", "velocity.strk", "./velocity.strk"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("both be the module `velocity`"));
+    let output = stork(&["check", "--root", "game", "velocity.strk"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("isn't in"));
+}
+
+#[test]
+fn explain() {
+    let output = stork(&["--explain", "e0004"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("E0004\n\nA name isn't declared"));
+
+    let output = stork(&["--explain", "E9999"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("E9999 isn't an error code"));
+}
+
+#[test]
+fn dump() {
+    let output = stork(&["dump", "movement.strk"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for section in [
+        "== movement CST ==",
+        "== movement AST ==",
+        "== movement HIR ==",
+    ] {
+        assert!(stdout.contains(section), "{section} missing from\n{stdout}");
+    }
+    assert!(stdout.contains("Number 2.0: ResolvedType"));
+}
+
+#[test]
+fn dump_broken() {
+    let output = stork(&["dump", "unclosed.strk", "movement.strk"]);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for section in [
+        "== unclosed CST ==",
+        "== unclosed HIR ==",
+        "== movement HIR ==",
+        "Unexpected end of file",
+    ] {
+        assert!(stdout.contains(section), "{section} missing from\n{stdout}");
+    }
+}
+
+#[test]
+fn run() {
+    let output = stork(&[
+        "run",
+        "movement.strk",
+        "--scene",
+        "scene.scn.ron",
+        "--system",
+        "movement::step",
+        "--frames",
+        "3",
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("translation: (6.0, 0.0, 0.0)"), "{stdout}");
+
+    let output = stork(&["run", "movement.strk", "--system", "nope"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("There's no system nope"));
+}
+
+#[test]
+fn fmt() {
+    let dir = std::env::temp_dir().join(format!("stork-fmt-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("messy.strk");
+    std::fs::write(&file, "use std\nsys step{print( 1 )}").unwrap();
+    let file = file.to_str().unwrap();
+    let root = dir.to_str().unwrap();
+
+    assert!(!stork(&["fmt", "--root", root, "--check", file])
+        .status
+        .success());
+    assert!(stork(&["fmt", "--root", root, file]).status.success());
+    assert_eq!(
+        std::fs::read_to_string(file).unwrap(),
+        "use std\nsys step {\n    print(1)\n}\n"
+    );
+    assert!(stork(&["fmt", "--root", root, "--check", file])
+        .status
+        .success());
+
+    let file = dir.join("invalid.strk");
+    std::fs::write(&file, "sys { } ? comp").unwrap();
+    assert!(!stork(&["fmt", "--root", root, file.to_str().unwrap()])
+        .status
+        .success());
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "sys { } ? comp");
+
+    let output = stork(&["fmt", "--check", "movement.strk", "broken.strk"]);
+    assert!(output.status.success());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn schema() {
+    // The game's own components are only known through its schema
+    assert!(!stork(&["check", "health.strk"]).status.success());
+    let output = stork(&["check", "health.strk", "--schema", "game.schema.json"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    let path = std::env::temp_dir().join(format!("stork-schema-{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+    assert!(stork(&["schema", path]).status.success());
+    assert!(stork(&["check", "movement.strk", "--schema", path])
+        .status
+        .success());
+    assert!(!stork(&["check", "health.strk", "--schema", path])
+        .status
+        .success());
+    std::fs::remove_file(path).unwrap();
+}
@@ -1,4 +1,5 @@
 use crate::cst::{StorkLang, SyntaxNode, SyntaxToken, Token};
+use crate::diagnostic::Code;
 use crate::module_index::ModuleID;
 use crate::report::{Report, Result, INTERNAL_REPORT_KIND};
 use rowan::{ast::AstNode, GreenNode, SyntaxElement, SyntaxElementChildren};
@@ -6,8 +7,9 @@ use std::fmt::{Debug, DebugTuple};
 
 pub fn run(cst: GreenNode, module_id: ModuleID) -> Result<Root> {
     let node = SyntaxNode::new_root(cst);
-    Root::cast(node)
-        .ok_or_else(|| Box::new(Report::build(INTERNAL_REPORT_KIND, module_id, 0).finish()))
+    Root::cast(node).ok_or_else(|| {
+        Box::new(Report::build(INTERNAL_REPORT_KIND, Code::Internal, module_id, 0).finish())
+    })
 }
 
 trait DebugTupleEx {
@@ -111,8 +113,17 @@ ast!(
         Resource,
         Component,
         Import,
+        Test,
     }
 );
+impl Item {
+    /// Whether it's declared `pub`, so other modules can use it.
+    pub fn is_public(&self) -> bool {
+        self.syntax()
+            .children_with_tokens()
+            .any(|t| t.kind() == Token::PUB)
+    }
+}
 ast!(struct System => Token::System);
 impl System {
     pub fn ident(&self) -> Option<String> {
@@ -165,18 +176,94 @@ impl Debug for Resource {
 }
 ast!(struct Import => Token::Import);
 impl Import {
-    pub fn ident(&self) -> Option<String> {
+    /// Segments joined with `::`, like `game::physics`.
+    pub fn path(&self) -> Option<String> {
+        let segments = self
+            .0
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .take_while(|t| t.kind() != Token::AS)
+            .filter(|t| t.kind() == Token::IDENT)
+            .map(|s| s.text().to_string())
+            .collect::<Vec<_>>();
+        (!segments.is_empty()).then(|| segments.join("::"))
+    }
+
+    /// The name after `as`.
+    pub fn alias(&self) -> Option<String> {
+        alias(&self.0)
+    }
+
+    /// The names in `{}`, if there are any braces.
+    pub fn names(&self) -> Option<Vec<ImportName>> {
+        let list = self.0.children().find(|n| n.kind() == Token::ImportList)?;
+        Some(list.children().filter_map(ImportName::cast).collect())
+    }
+}
+impl Debug for Import {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut f = f.debug_tuple(&format!("Import @{:?}", self.0.text_range()));
+        f.option_field(&self.path());
+        if let Some(alias) = self.alias() {
+            f.field(&alias);
+        }
+        if let Some(names) = self.names() {
+            f.field(&names);
+        }
+        f.finish()
+    }
+}
+
+ast!(struct ImportName => Token::ImportName);
+impl ImportName {
+    pub fn name(&self) -> Option<String> {
         self.0
             .children_with_tokens()
             .filter_map(SyntaxElement::into_token)
             .find(|t| t.kind() == Token::IDENT)
             .map(|s| s.text().to_string())
     }
+
+    pub fn alias(&self) -> Option<String> {
+        alias(&self.0)
+    }
 }
-impl Debug for Import {
+impl Debug for ImportName {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_tuple(&format!("Import @{:?}", self.0.text_range()))
-            .option_field(&self.ident())
+        f.debug_tuple(&format!("ImportName @{:?}", self.0.text_range()))
+            .option_field(&self.name())
+            .option_field(&self.alias())
+            .finish()
+    }
+}
+
+fn alias(node: &SyntaxNode) -> Option<String> {
+    node.children_with_tokens()
+        .filter_map(SyntaxElement::into_token)
+        .skip_while(|t| t.kind() != Token::AS)
+        .find(|t| t.kind() == Token::IDENT)
+        .map(|s| s.text().to_string())
+}
+
+ast!(struct Test => Token::Test);
+impl Test {
+    pub fn name(&self) -> Option<String> {
+        self.0
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .find(|t| t.kind() == Token::STRING)
+            .map(|s| unquote(s.text()))
+    }
+
+    pub fn block(&self) -> Option<Block> {
+        self.0.children().find_map(Block::cast)
+    }
+}
+impl Debug for Test {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple(&format!("Test @{:?}", self.0.text_range()))
+            .option_field(&self.name())
+            .option_field(&self.block())
             .finish()
     }
 }
@@ -468,12 +555,30 @@ impl Literal {
             .ok()
     }
 
-    pub fn as_identifier(&self) -> Option<String> {
+    pub fn as_string(&self) -> Option<String> {
         self.0
             .children_with_tokens()
             .filter_map(SyntaxElement::into_token)
-            .find(|t| t.kind() == Token::IDENT)
+            .find(|t| t.kind() == Token::STRING)
+            .map(|s| unquote(s.text()))
+    }
+
+    /// Only unqualified identifiers, see [`Literal::as_path`].
+    pub fn as_identifier(&self) -> Option<String> {
+        let [ident] = <[String; 1]>::try_from(self.as_path()?).ok()?;
+        Some(ident)
+    }
+
+    /// The segments of an identifier, several if it's qualified like `a::b`.
+    pub fn as_path(&self) -> Option<Vec<String>> {
+        let segments = self
+            .0
+            .children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+            .filter(|t| t.kind() == Token::IDENT)
             .map(|s| s.text().to_string())
+            .collect::<Vec<_>>();
+        (!segments.is_empty()).then_some(segments)
     }
 }
 impl Debug for Literal {
@@ -524,11 +629,11 @@ impl Debug for Block {
 
 ast!(struct Struct => Token::Struct);
 impl Struct {
-    pub fn ident(&self) -> Option<String> {
+    pub fn path(&self) -> Option<Vec<String>> {
         self.0
             .children()
             .find_map(Literal::cast)
-            .and_then(|l| l.as_identifier())
+            .and_then(|l| l.as_path())
     }
 
     pub fn fields(&self) -> impl Iterator<Item = (String, Expr)> {
@@ -562,7 +667,7 @@ impl Struct {
 impl Debug for Struct {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut f = f.debug_tuple(&format!("Struct @{:?}", self.0.text_range()));
-        f.option_field(&self.ident());
+        f.option_field(&self.path().map(|path| path.join("::")));
         for expr in self.fields() {
             f.field(&expr);
         }
@@ -570,5 +675,11 @@ impl Debug for Struct {
     }
 }
 
+/// Strings can't contain quotes, so this only strips the ones around it.
+fn unquote(text: &str) -> String {
+    let text = text.strip_prefix('"').unwrap_or(text);
+    text.strip_suffix('"').unwrap_or(text).to_string()
+}
+
 #[cfg(test)]
 mod test;
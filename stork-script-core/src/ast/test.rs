@@ -1,4 +1,4 @@
-use super::Root;
+use super::{unquote, Root};
 use crate::cst::{Parser, SyntaxNode};
 use expect_test::{expect, Expect};
 use rowan::ast::AstNode;
@@ -396,3 +396,86 @@ fn test8() {
             )"#]],
     );
 }
+
+#[test]
+fn test9() {
+    check(
+        r#"
+    test "a test" {
+        assert(x, "msg")
+    }"#,
+        expect![[r#"
+            Root @0..51(
+                Test @5..51(
+                    "a test",
+                    Block @19..51(
+                        Call @29..45(
+                            Literal @29..35(
+                                "assert",
+                            ),
+                            [
+                                Literal @36..37(
+                                    "x",
+                                ),
+                                Literal @39..44(
+                                    "\"msg\"",
+                                ),
+                            ],
+                        ),
+                    ),
+                ),
+            )"#]],
+    );
+}
+
+#[test]
+fn imports() {
+    check(
+        "use std::{print as p}\nsys { e[phys::V] = game::physics::V { x: 1 }; }",
+        expect![[r#"
+            Root @0..69(
+                Import @0..21(
+                    "std",
+                    [
+                        ImportName @10..20(
+                            "print",
+                            "p",
+                        ),
+                    ],
+                ),
+                System @22..69(
+                    "Option::None",
+                    Block @26..69(
+                        BinaryExpr @28..66(
+                            ECSAccess @28..38(
+                                Some(
+                                    Literal @28..29(
+                                        "e",
+                                    ),
+                                ),
+                                Literal @30..37(
+                                    "phys::V",
+                                ),
+                            ),
+                            EQ@39..40 "=",
+                            Struct @41..66(
+                                "game::physics::V",
+                                (
+                                    "x",
+                                    Literal @63..64(
+                                        "1",
+                                    ),
+                                ),
+                            ),
+                        ),
+                    ),
+                ),
+            )"#]],
+    );
+}
+
+#[test]
+fn unquoted_strings() {
+    assert_eq!(unquote(r#""a test""#), "a test");
+    assert_eq!(unquote(r#""""#), "");
+}
@@ -4,6 +4,7 @@ use rowan::{Checkpoint, GreenNode, GreenNodeBuilder, Language, SyntaxKind};
 use std::ops::Range;
 
 use crate::{
+    diagnostic::Code,
     module_index::ModuleID,
     report::{Label, Report, ReportBuilder, Result, INTERNAL_REPORT_KIND},
 };
@@ -14,6 +15,19 @@ pub fn run(source: &str, module_id: ModuleID) -> Result<(GreenNode, Vec<Report>)
     Ok((parser.parse()?, errors))
 }
 
+/// Whether `text` lexes as a single identifier, keywords don't.
+pub fn is_identifier(text: &str) -> bool {
+    let mut errors = Vec::new();
+    let mut lexer = Token::lexer_with_extras(
+        text,
+        ParseCtx {
+            module_id: 0,
+            errors: &mut errors,
+        },
+    );
+    matches!(lexer.next(), Some(Ok(Token::IDENT))) && lexer.next().is_none()
+}
+
 pub struct ParseCtx<'e> {
     module_id: ModuleID,
     errors: &'e mut Vec<Report>,
@@ -47,6 +61,11 @@ pub enum Token {
     DEL,
     #[token("use")]
     USE,
+    // Contextual keywords, these lex as identifiers so they can still be used
+    // as names. The parser turns them into keywords where they are ones.
+    TEST,
+    AS,
+    PUB,
 
     // Whitespace
     #[regex(r"[ \t]+")]
@@ -63,6 +82,8 @@ pub enum Token {
     NUMBER,
     #[regex(r"[_a-zA-Z][0-9a-zA-Z_]*")]
     IDENT,
+    #[regex(r#""[^"\n]*""#)]
+    STRING,
 
     // "Operators"
     #[token("+")]
@@ -83,6 +104,8 @@ pub enum Token {
     SLASHEQ,
     #[token(":")]
     COLON,
+    #[token("::")]
+    COLONCOLON,
     #[token("{")]
     LBRACE,
     #[token("}")]
@@ -128,6 +151,9 @@ pub enum Token {
     System,
     Function,
     Import,
+    ImportList,
+    ImportName,
+    Test,
 
     // Composite, types
     FieldType,
@@ -242,9 +268,10 @@ impl<'a> Parser<'a> {
         self.span.clone()
     }
 
-    fn report(&self) -> ReportBuilder {
+    fn report(&self, code: Code) -> ReportBuilder {
         Report::build(
             ReportKind::Error,
+            code,
             self.iter.extras.module_id,
             self.span.start,
         )
@@ -253,6 +280,7 @@ impl<'a> Parser<'a> {
     fn report_internal(&self) -> ReportBuilder {
         Report::build(
             INTERNAL_REPORT_KIND,
+            Code::Internal,
             self.iter.extras.module_id,
             self.span.start,
         )
@@ -298,7 +326,7 @@ impl<'a> Parser<'a> {
         let mut error = None;
         while self.token != token && self.token != Token::EOF {
             error = error.or(Some(
-                self.report()
+                self.report(Code::UnexpectedToken)
                     .with_label(self.label("here"))
                     .with_message("Unexpected token")
                     .with_help(format!("Expected {token:?}"))
@@ -312,6 +340,56 @@ impl<'a> Parser<'a> {
         Ok(())
     }
 
+    /// The first token from here on that isn't whitespace on the same line,
+    /// and its text, without consuming anything.
+    fn peek_past_ws(&self) -> Option<(Token, &'a str)> {
+        let mut errors = Vec::new();
+        let mut lexer = Token::lexer_with_extras(
+            &self.source[self.span.start..],
+            ParseCtx {
+                module_id: self.iter.extras.module_id,
+                errors: &mut errors,
+            },
+        );
+        while let Some(token) = lexer.next() {
+            let token = token.unwrap_or(Token::UNKNOWN);
+            if token != Token::WHITE_SPACE {
+                let span = lexer.span();
+                let text = &self.source[self.span.start + span.start..self.span.start + span.end];
+                return Some((token, text));
+            }
+        }
+        None
+    }
+
+    /// Whether the token here is the contextual keyword `keyword`, spelled
+    /// `text`. If it is, it becomes that keyword instead of an identifier.
+    fn at_keyword(&mut self, keyword: Token, text: &str) -> bool {
+        if self.token == Token::IDENT && self.source.get(self.span()) == Some(text) {
+            self.token = keyword;
+        }
+        self.token == keyword
+    }
+
+    /// Whether a block or list ends here, either properly or because the source does.
+    fn at_close(&self, token: Token) -> bool {
+        self.token == token || self.token == Token::EOF
+    }
+
+    fn close(&mut self, token: Token) -> Result<()> {
+        if self.token != Token::EOF {
+            return self.bump();
+        }
+        self.iter.extras.errors.push(
+            self.report(Code::UnexpectedEndOfFile)
+                .with_label(self.label("here"))
+                .with_message("Unexpected end of file")
+                .with_help(format!("Expected {token:?}"))
+                .finish(),
+        );
+        Ok(())
+    }
+
     fn leaf(&mut self, token: Token) -> Result<()> {
         self.builder.start_node(token.into());
         self.bump()?;
@@ -358,25 +436,43 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_item(&mut self) -> Result<()> {
+        // `pub` is part of the item after it
+        let checkpoint = self.checkpoint();
+        if self.at_keyword(Token::PUB, "pub") {
+            self.bump()?;
+            self.eat_ws()?;
+            if !matches!(self.token, Token::COMP | Token::RES | Token::SYS) {
+                let report = self
+                    .report(Code::UnexpectedToken)
+                    .with_label(self.label("here"))
+                    .with_message("Only components, resources and systems can be `pub`")
+                    .finish();
+                self.iter.extras.errors.push(report);
+                return Ok(());
+            }
+        }
+        // No other item starts with an identifier
+        self.at_keyword(Token::TEST, "test");
         match self.token {
-            Token::COMP => self.parse_component(),
-            Token::RES => self.parse_resource(),
-            Token::SYS => self.parse_system(),
+            Token::COMP => self.parse_component(checkpoint),
+            Token::RES => self.parse_resource(checkpoint),
+            Token::SYS => self.parse_system(checkpoint),
             Token::USE => self.parse_import(),
+            Token::TEST => self.parse_test(),
             _ => self.leaf(Token::Error),
         }
     }
 
-    fn parse_component(&mut self) -> Result<()> {
-        self.node(Token::Component, |s| s.parse_field_def())
+    fn parse_component(&mut self, checkpoint: Checkpoint) -> Result<()> {
+        self.checkpoint_node(checkpoint, Token::Component, |s| s.parse_field_def())
     }
 
-    fn parse_resource(&mut self) -> Result<()> {
-        self.node(Token::Resource, |s| s.parse_field_def())
+    fn parse_resource(&mut self, checkpoint: Checkpoint) -> Result<()> {
+        self.checkpoint_node(checkpoint, Token::Resource, |s| s.parse_field_def())
     }
 
-    fn parse_system(&mut self) -> Result<()> {
-        self.node(Token::System, |s| {
+    fn parse_system(&mut self, checkpoint: Checkpoint) -> Result<()> {
+        self.checkpoint_node(checkpoint, Token::System, |s| {
             if s.token == Token::IDENT {
                 s.bump()?;
                 s.eat_ws()?;
@@ -386,16 +482,86 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// `use a::b`, `use a::b as c` or `use a::{b, c as d}`.
     fn parse_import(&mut self) -> Result<()> {
         self.node(Token::Import, |s| {
             s.expect(Token::IDENT)?;
-            s.bump()
+            s.bump()?;
+            while s.token == Token::COLONCOLON {
+                s.bump()?;
+                if s.token == Token::LBRACE {
+                    return s.parse_import_list();
+                }
+                s.expect(Token::IDENT)?;
+                s.bump()?;
+            }
+            // Trailing whitespace isn't part of the import
+            if s.token == Token::WHITE_SPACE && s.peek_past_ws() == Some((Token::IDENT, "as")) {
+                s.bump()?;
+            }
+            if s.at_keyword(Token::AS, "as") {
+                s.bump()?;
+                s.eat_ws()?;
+                s.expect(Token::IDENT)?;
+                s.bump()?;
+            }
+            Ok(())
+        })
+    }
+
+    fn parse_import_list(&mut self) -> Result<()> {
+        self.node(Token::ImportList, |s| {
+            while !s.at_close(Token::RBRACE) {
+                s.expect(Token::IDENT)?;
+                s.node(Token::ImportName, |s| {
+                    if s.at_keyword(Token::AS, "as") {
+                        s.bump()?;
+                        s.eat_ws()?;
+                        s.expect(Token::IDENT)?;
+                        s.bump()?;
+                        s.eat_ws()?;
+                    }
+                    Ok(())
+                })?;
+                if s.token == Token::COMMA {
+                    s.bump()?;
+                    s.eat_ws()?;
+                } else {
+                    s.expect(Token::RBRACE)?;
+                }
+            }
+            s.close(Token::RBRACE)
+        })
+    }
+
+    /// An identifier, possibly qualified with a module path like `a::b::c`.
+    fn parse_path(&mut self) -> Result<()> {
+        self.builder.start_node(Token::Literal.into());
+        self.bump()?;
+        while self.token == Token::COLONCOLON {
+            self.bump()?;
+            if self.token != Token::IDENT {
+                break;
+            }
+            self.bump()?;
+        }
+        self.builder.finish_node();
+        Ok(())
+    }
+
+    fn parse_test(&mut self) -> Result<()> {
+        self.node(Token::Test, |s| {
+            s.expect(Token::STRING)?;
+            s.bump()?;
+            s.eat_ws()?;
+            s.expect(Token::LBRACE)?;
+            s.parse_block()
         })
     }
 
     fn parse_block(&mut self) -> Result<()> {
         self.node(Token::Block, |s| {
-            while s.token != Token::RBRACE {
+            while !s.at_close(Token::RBRACE) {
                 let parsed_block = s.parse_expr(None)?;
                 s.eat_ws()?;
                 if s.token == Token::SEMICOLON {
@@ -405,7 +571,7 @@ impl<'a> Parser<'a> {
                     s.expect(Token::RBRACE)?;
                 }
             }
-            s.bump()
+            s.close(Token::RBRACE)
         })
     }
 
@@ -414,15 +580,15 @@ impl<'a> Parser<'a> {
         let mut parsed_block = ParsedBlock::No;
 
         match self.token {
-            Token::NUMBER => self.leaf(Token::Literal)?,
+            Token::NUMBER | Token::STRING => self.leaf(Token::Literal)?,
             Token::IDENT => {
                 let struct_checkpoint = self.checkpoint();
-                self.leaf(Token::Literal)?;
+                self.parse_path()?;
 
                 self.eat_ws()?;
                 if self.token == Token::LBRACE {
                     self.checkpoint_node(struct_checkpoint, Token::Struct, |s| {
-                        while s.token != Token::RBRACE {
+                        while !s.at_close(Token::RBRACE) {
                             s.expect(Token::IDENT)?;
                             s.bump()?;
                             s.eat_ws()?;
@@ -437,7 +603,7 @@ impl<'a> Parser<'a> {
                                 s.expect(Token::RBRACE)?;
                             }
                         }
-                        s.bump()
+                        s.close(Token::RBRACE)
                     })?;
                 }
             }
@@ -449,7 +615,7 @@ impl<'a> Parser<'a> {
                 self.node(Token::Paren, |s| {
                     s.parse_expr(None)?;
                     s.expect(Token::RPAREN)?;
-                    s.bump()
+                    s.close(Token::RPAREN)
                 })?;
             }
             Token::LBRACE => {
@@ -505,7 +671,7 @@ impl<'a> Parser<'a> {
                 self.node(Token::ResourceAccess, |s| {
                     s.parse_expr(None)?;
                     s.expect(Token::RBRACKET)?;
-                    s.bump()
+                    s.close(Token::RBRACKET)
                 })?;
             }
             token if token.is_prefix_op() => {
@@ -521,7 +687,7 @@ impl<'a> Parser<'a> {
             }
             _ => {
                 self.iter.extras.errors.push(
-                    self.report()
+                    self.report(Code::UnexpectedToken)
                         .with_label(self.label("here"))
                         .with_message("Unexpected token")
                         .finish(),
@@ -546,11 +712,11 @@ impl<'a> Parser<'a> {
                 self.checkpoint_node(checkpoint, Token::ComponentAccess, |s| {
                     s.parse_expr(None)?;
                     s.expect(Token::RBRACKET)?;
-                    s.bump()
+                    s.close(Token::RBRACKET)
                 })?;
             } else if self.token == Token::LPAREN {
                 self.checkpoint_node(checkpoint, Token::Call, |s| {
-                    while s.token != Token::RPAREN {
+                    while !s.at_close(Token::RPAREN) {
                         s.parse_expr(None)?;
                         s.eat_ws()?;
                         if s.token == Token::COMMA {
@@ -560,7 +726,7 @@ impl<'a> Parser<'a> {
                             s.expect(Token::RPAREN)?;
                         }
                     }
-                    s.bump()
+                    s.close(Token::RPAREN)
                 })?;
             } else {
                 self.checkpoint_node(checkpoint, Token::Infix, |s| {
@@ -585,7 +751,7 @@ impl<'a> Parser<'a> {
 
     fn parse_struct_def(&mut self) -> Result<()> {
         self.node(Token::StructType, |s| {
-            while s.token != Token::RBRACE {
+            while !s.at_close(Token::RBRACE) {
                 s.parse_field_def()?;
                 s.eat_ws()?;
                 if s.token == Token::COMMA {
@@ -595,7 +761,7 @@ impl<'a> Parser<'a> {
                     s.expect(Token::RBRACE)?;
                 }
             }
-            s.bump()
+            s.close(Token::RBRACE)
         })?;
         Ok(())
     }
@@ -486,3 +486,151 @@ fn test11() {
         "#]],
     );
 }
+
+#[test]
+fn test12() {
+    check(
+        r#"
+          test "a test" {
+               assert(1 == 1, "msg")
+          }
+        "#,
+        expect![[r#"
+            Root @0..84
+                 Test @11..75
+                      TEST @11..15
+                      STRING @16..24
+                      Block @25..75
+                           LBRACE @25..26
+                           Call @42..63
+                                Literal @42..48
+                                     IDENT @42..48
+                                LPAREN @48..49
+                                Infix @49..55
+                                     Literal @49..50
+                                          NUMBER @49..50
+                                     EQEQ @51..53
+                                     Literal @54..55
+                                          NUMBER @54..55
+                                COMMA @55..56
+                                Literal @57..62
+                                     STRING @57..62
+                                RPAREN @62..63
+                           RBRACE @74..75
+        "#]],
+    );
+}
+
+#[test]
+fn imports() {
+    check(
+        "use game::physics as phys\nuse std::{print, Vec as V}\n",
+        expect![[r#"
+            Root @0..53
+                 Import @0..25
+                      USE @0..3
+                      IDENT @4..8
+                      COLONCOLON @8..10
+                      IDENT @10..17
+                      AS @18..20
+                      IDENT @21..25
+                 Import @26..52
+                      USE @26..29
+                      IDENT @30..33
+                      COLONCOLON @33..35
+                      ImportList @35..52
+                           LBRACE @35..36
+                           ImportName @36..41
+                                IDENT @36..41
+                           COMMA @41..42
+                           ImportName @43..51
+                                IDENT @43..46
+                                AS @47..49
+                                IDENT @50..51
+                           RBRACE @51..52
+        "#]],
+    );
+}
+
+#[test]
+fn public_items() {
+    check(
+        "pub comp V: f32\npub sys s {}\n",
+        expect![[r#"
+            Root @0..29
+                 Component @0..15
+                      PUB @0..3
+                      COMP @4..8
+                      FieldType @9..15
+                           IDENT @9..10
+                           COLON @10..11
+                           Literal @12..15
+                                IDENT @12..15
+                 System @16..28
+                      PUB @16..19
+                      SYS @20..23
+                      IDENT @24..25
+                      Block @26..28
+                           LBRACE @26..27
+                           RBRACE @27..28
+        "#]],
+    );
+
+    // Only components, resources and systems can be `pub`
+    let mut errors = Vec::new();
+    let result = Parser::new("pub use std\n", 0, &mut errors)
+        .parse()
+        .unwrap();
+    assert_eq!(result.to_string(), "pub use std\n");
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn contextual_keywords() {
+    check(
+        "use a::{test as as}\nsys { let pub = test; }\n",
+        expect![[r#"
+            Root @0..44
+                 Import @0..19
+                      USE @0..3
+                      IDENT @4..5
+                      COLONCOLON @5..7
+                      ImportList @7..19
+                           LBRACE @7..8
+                           ImportName @8..18
+                                IDENT @8..12
+                                AS @13..15
+                                IDENT @16..18
+                           RBRACE @18..19
+                 System @20..43
+                      SYS @20..23
+                      Block @24..43
+                           LBRACE @24..25
+                           Let @26..40
+                                LET @26..29
+                                Literal @30..33
+                                     IDENT @30..33
+                                EQ @34..35
+                                Literal @36..40
+                                     IDENT @36..40
+                           SEMICOLON @40..41
+                           RBRACE @42..43
+        "#]],
+    );
+}
+
+#[test]
+fn unterminated() {
+    for source in [
+        "sys { ",
+        "sys { print(1",
+        "sys { [Health",
+        "comp A: { x: f32",
+        "sys { A { x: 1",
+    ] {
+        let mut errors = Vec::new();
+        let result = Parser::new(source, 0, &mut errors).parse().unwrap();
+        assert_eq!(result.to_string(), source);
+        assert!(!errors.is_empty(), "{source:?} should have errors");
+    }
+}
@@ -0,0 +1,386 @@
+//! Reports in a serializable form with stable codes, for tools that can't
+//! read ariadne's output, like build scripts and editors.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    module_index::{ModuleCollection, ModuleID},
+    report::{Report, ReportKind, Span},
+};
+
+/// What a report is about. Once released a code keeps its meaning, new kinds
+/// of reports get new codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Code {
+    /// A bug in the compiler rather than in the script
+    Internal,
+    UnexpectedToken,
+    UnexpectedEndOfFile,
+    InvalidSyntax,
+    UnknownName,
+    UnknownType,
+    RecursiveType,
+    NotAFunction,
+    TooManyArguments,
+    TooFewArguments,
+    FieldNameNotLiteral,
+    NotAStruct,
+    UnknownField,
+    MismatchedTypes,
+    NotACondition,
+    NotFromEcs,
+    UnformattableSyntax,
+    TestFailed,
+    DuplicateDefinition,
+    AmbiguousName,
+    UnknownModule,
+    ImportCycle,
+    PrivateItem,
+    TestOnly,
+    UnusedVariable,
+    UnusedImport,
+    UnusedItem,
+    ShadowedBuiltin,
+}
+
+impl Code {
+    pub const ALL: [Code; 28] = [
+        Code::Internal,
+        Code::UnexpectedToken,
+        Code::UnexpectedEndOfFile,
+        Code::InvalidSyntax,
+        Code::UnknownName,
+        Code::UnknownType,
+        Code::RecursiveType,
+        Code::NotAFunction,
+        Code::TooManyArguments,
+        Code::TooFewArguments,
+        Code::FieldNameNotLiteral,
+        Code::NotAStruct,
+        Code::UnknownField,
+        Code::MismatchedTypes,
+        Code::NotACondition,
+        Code::NotFromEcs,
+        Code::UnformattableSyntax,
+        Code::TestFailed,
+        Code::DuplicateDefinition,
+        Code::AmbiguousName,
+        Code::UnknownModule,
+        Code::ImportCycle,
+        Code::PrivateItem,
+        Code::TestOnly,
+        Code::UnusedVariable,
+        Code::UnusedImport,
+        Code::UnusedItem,
+        Code::ShadowedBuiltin,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Code::Internal => "E0000",
+            Code::UnexpectedToken => "E0001",
+            Code::UnexpectedEndOfFile => "E0002",
+            Code::InvalidSyntax => "E0003",
+            Code::UnknownName => "E0004",
+            Code::UnknownType => "E0005",
+            Code::RecursiveType => "E0006",
+            Code::NotAFunction => "E0007",
+            Code::TooManyArguments => "E0008",
+            Code::TooFewArguments => "E0009",
+            Code::FieldNameNotLiteral => "E0010",
+            Code::NotAStruct => "E0011",
+            Code::UnknownField => "E0012",
+            Code::MismatchedTypes => "E0013",
+            Code::NotACondition => "E0014",
+            Code::NotFromEcs => "E0015",
+            Code::UnformattableSyntax => "E0016",
+            Code::TestFailed => "E0017",
+            Code::DuplicateDefinition => "E0018",
+            Code::AmbiguousName => "E0019",
+            Code::UnknownModule => "E0020",
+            Code::ImportCycle => "E0021",
+            Code::PrivateItem => "E0022",
+            Code::TestOnly => "E0023",
+            Code::UnusedVariable => "W0001",
+            Code::UnusedImport => "W0002",
+            Code::UnusedItem => "W0003",
+            Code::ShadowedBuiltin => "W0004",
+        }
+    }
+
+    /// The name that silences a warning with `#[allow(name)]`.
+    pub fn lint(self) -> Option<&'static str> {
+        Some(match self {
+            Code::UnusedVariable => "unused_variables",
+            Code::UnusedImport => "unused_imports",
+            Code::UnusedItem => "dead_code",
+            Code::ShadowedBuiltin => "shadowed_builtins",
+            _ => return None,
+        })
+    }
+
+    /// Case insensitive, so `e0004` works too.
+    pub fn parse(code: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|known| known.as_str().eq_ignore_ascii_case(code))
+    }
+
+    /// A longer description with an example, for `stork --explain`. Warnings can
+    /// be silenced for an item with a `#[allow(lint)]` comment above it.
+    pub fn explanation(self) -> &'static str {
+        match self {
+            Code::Internal => {
+                "The compiler ran into a bug, this isn't a problem with the script.\n\
+                 Please report it together with the script that caused it."
+            }
+            Code::UnexpectedToken => {
+                "The parser found a token where it doesn't fit, like a missing\n\
+                 closing bracket:\n\n    sys { print(1 }\n\n\
+                 The help says which token was expected instead."
+            }
+            Code::UnexpectedEndOfFile => {
+                "The file ended before something was closed:\n\n    sys {\n\n\
+                 Close every `{`, `(` and `[`."
+            }
+            Code::InvalidSyntax => {
+                "An expression couldn't be parsed, so it's treated as unknown.\n\
+                 It's usually reported together with a more specific syntax error."
+            }
+            Code::UnknownName => {
+                "A name isn't declared in scope:\n\n    sys { let a = b; }\n\n\
+                 Declare it with `let`, or `use` the module that declares it.\n\
+                 Locals are only visible after they're declared. A qualified\n\
+                 name like `physics::Velocity` must be declared in that module."
+            }
+            Code::UnknownType => {
+                "A type in a component, resource or struct literal isn't declared:\n\n    \
+                 comp Velocity: Vec\n\n\
+                 Types come from `std` or from components declared in imported modules."
+            }
+            Code::RecursiveType => {
+                "A type contains itself, so it would be infinitely large:\n\n    \
+                 comp A: B\n    comp B: A"
+            }
+            Code::NotAFunction => {
+                "Something that isn't a function is called:\n\n    \
+                 sys { let a = 1; a(); }"
+            }
+            Code::TooManyArguments => {
+                "A function is called with more arguments than it takes:\n\n    \
+                 sys { print(1, 2) }"
+            }
+            Code::TooFewArguments => {
+                "A function is called with fewer arguments than it takes:\n\n    \
+                 sys { print() }"
+            }
+            Code::FieldNameNotLiteral => {
+                "Fields can only be accessed by their name, after a `.`:\n\n    \
+                 e[Transform].translation"
+            }
+            Code::NotAStruct => {
+                "A field is accessed on something that has no fields:\n\n    \
+                 sys { let a = 1; a.x = 2; }"
+            }
+            Code::UnknownField => {
+                "A struct literal or a field access uses a field its type doesn't have:\n\n    \
+                 comp Velocity: { x: f32 }\n    sys { let v = Velocity { y: 1 }; }\n\n\
+                 The help suggests the closest field that it does have."
+            }
+            Code::MismatchedTypes => {
+                "A value has a different type than expected, like an argument\n\
+                 or the value assigned to a component:\n\n    \
+                 comp Health: f32\n    sys { query e { e[Health] = \"full\"; } }"
+            }
+            Code::NotACondition => {
+                "Conditions of `if` and `while`, and operands of `!`, `&&` and `||`\n\
+                 need to be `bool`, or an ECS access that checks whether it exists:\n\n    \
+                 sys { if 1 { } }"
+            }
+            Code::NotFromEcs => {
+                "Only components and resources can be accessed on entities and the world,\n\
+                 inserted with `let` or removed with `del`:\n\n    \
+                 sys { query e { del e[f32]; } }"
+            }
+            Code::UnformattableSyntax => {
+                "The formatter refuses modules with syntax errors instead of risking\n\
+                 mangling them. Fix the syntax errors first."
+            }
+            Code::TestFailed => {
+                "A `test` item panicked or one of its `assert`s failed. The label\n\
+                 points at the failed `assert` and says what its message was."
+            }
+            Code::DuplicateDefinition => {
+                "A module declares two items with the same name:\n\n    \
+                 comp Velocity: f32\n    comp Velocity: { x: f32 }\n\n\
+                 Only the first one can be referred to. Items may share a name with\n\
+                 imported ones though, which they shadow, and locals shadow both."
+            }
+            Code::AmbiguousName => {
+                "A name is used that several imported modules declare:\n\n    \
+                 use physics\n    use health\n    sys { query e { e[Velocity] = 1; } }\n\n\
+                 Import only one of them, or declare the name in the module itself,\n\
+                 which shadows imports."
+            }
+            Code::UnknownModule => {
+                "A `use` or a qualified name refers to a module that doesn't exist:\n\n    \
+                 use sdt\n\n\
+                 The help suggests the closest module that does. The rest of the\n\
+                 module is still checked, without names from the missing one."
+            }
+            Code::ImportCycle => {
+                "Modules import each other, directly or through other modules:\n\n    \
+                 # physics\n    use main\n    # main\n    use physics\n\n\
+                 Move what both need into a third module that neither imports."
+            }
+            Code::PrivateItem => {
+                "An item of another module is used, but it isn't declared `pub`:\n\n    \
+                 # physics\n    comp Velocity: f32\n    # main\n    use physics\n    \
+                 sys { query e { e[Velocity] = 1; } }\n\n\
+                 Items are only visible in their own module unless they're `pub`,\n\
+                 like `pub comp Velocity: f32`."
+            }
+            Code::TestOnly => {
+                "A system calls another system, or a builtin that needs the whole\n\
+                 world like `spawn` or `assert`:\n\n    \
+                 sys setup { let e = spawn(); }\n\n\
+                 Systems run alongside each other and only get the components and\n\
+                 resources they use. Only `test` items, which run in a world of\n\
+                 their own, can do these."
+            }
+            Code::UnusedVariable => {
+                "A `let` binding is never used:\n\n    sys { let a = 1; }\n\n\
+                 Remove it, or start its name with `_` if it's intended."
+            }
+            Code::UnusedImport => {
+                "Nothing from an imported module is used:\n\n    use physics\n    sys { }"
+            }
+            Code::UnusedItem => {
+                "A private component or resource is declared but never accessed:\n\n    \
+                 comp Velocity: f32\n\n\
+                 `pub` items aren't reported, they can be used by modules that\n\
+                 aren't checked with this one."
+            }
+            Code::ShadowedBuiltin => {
+                "A local has the same name as something from `std`, which can't be\n\
+                 used in its scope anymore:\n\n    sys { let print = 1; }"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Advice,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub code: String,
+    pub severity: Severity,
+    pub message: String,
+    pub span: DiagnosticSpan,
+    /// Every label after the first, which is `span`
+    pub labels: Vec<DiagnosticLabel>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+    pub fixes: Vec<DiagnosticFix>,
+}
+
+/// Byte offsets, and the zero-indexed line and byte column where it starts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticSpan {
+    pub module: String,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticLabel {
+    pub span: DiagnosticSpan,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticFix {
+    pub title: String,
+    pub edits: Vec<DiagnosticEdit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticEdit {
+    pub span: DiagnosticSpan,
+    pub text: String,
+}
+
+impl Diagnostic {
+    pub fn new(report: &Report, modules: &ModuleCollection) -> Self {
+        let span = |span: &Span| DiagnosticSpan::new(span, modules);
+        let severity = match report.kind {
+            ReportKind::Warning => Severity::Warning,
+            ReportKind::Advice => Severity::Advice,
+            ReportKind::Error | ReportKind::Custom(..) => Severity::Error,
+        };
+        Self {
+            code: report.code.as_str().to_string(),
+            severity,
+            message: report.message.clone().unwrap_or_default(),
+            span: span(&report.span()),
+            labels: report
+                .labels
+                .iter()
+                .skip(1)
+                .map(|label| DiagnosticLabel {
+                    span: span(&label.span),
+                    message: label.message.clone(),
+                })
+                .collect(),
+            notes: report.note.iter().cloned().collect(),
+            help: report.help.clone(),
+            fixes: report
+                .fixes
+                .iter()
+                .map(|fix| DiagnosticFix {
+                    title: fix.title.clone(),
+                    edits: fix
+                        .edits
+                        .iter()
+                        .map(|edit| DiagnosticEdit {
+                            span: span(&edit.span),
+                            text: edit.text.clone(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl DiagnosticSpan {
+    fn new((module_id, range): &Span, modules: &ModuleCollection) -> Self {
+        let (line, column) = line_col(modules, *module_id, range.start);
+        Self {
+            module: modules.id_to_path(*module_id).to_string(),
+            start: range.start,
+            end: range.end,
+            line,
+            column,
+        }
+    }
+}
+
+fn line_col(modules: &ModuleCollection, module_id: ModuleID, offset: usize) -> (usize, usize) {
+    modules
+        .get_ref(module_id)
+        .source
+        .get_byte_line(offset)
+        .map_or((0, 0), |(_, line, column)| (line, column))
+}
+
+#[cfg(test)]
+mod test;
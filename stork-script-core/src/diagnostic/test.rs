@@ -0,0 +1,22 @@
+use std::collections::HashSet;
+
+use super::Code;
+
+#[test]
+fn codes_round_trip() {
+    for code in Code::ALL {
+        assert_eq!(Code::parse(code.as_str()), Some(code));
+        assert_eq!(Code::parse(&code.as_str().to_lowercase()), Some(code));
+    }
+    assert_eq!(Code::parse("E9999"), None);
+}
+
+#[test]
+fn codes_are_unique() {
+    let codes = Code::ALL.map(Code::as_str);
+    assert_eq!(codes.iter().collect::<HashSet<_>>().len(), codes.len());
+    // Only warnings can be silenced
+    for code in Code::ALL {
+        assert_eq!(code.lint().is_some(), code.as_str().starts_with('W'));
+    }
+}
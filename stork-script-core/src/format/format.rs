@@ -0,0 +1,268 @@
+use rowan::NodeOrToken;
+
+use crate::{
+    cst::{self, SyntaxElement, SyntaxNode, SyntaxToken, Token},
+    diagnostic::Code,
+    module_index::ModuleID,
+    report::{Label, Report, ReportKind, Result},
+};
+
+const INDENT: &str = "    ";
+
+/// Formats a module while keeping its comments. Modules with syntax errors
+/// are refused instead of risking mangling them.
+pub fn run(source: &str, module_id: ModuleID) -> Result<String> {
+    let (green, errors) = cst::run(source, module_id)?;
+    let root = SyntaxNode::new_root(green);
+
+    if let Some(error) = root
+        .descendants_with_tokens()
+        .find(|element| matches!(element.kind(), Token::Error | Token::UNKNOWN))
+    {
+        let range = error.text_range();
+        return Err(Box::new(
+            Report::build(
+                ReportKind::Error,
+                Code::UnformattableSyntax,
+                module_id,
+                range.start().into(),
+            )
+            .with_message("Can't format a module with syntax errors")
+            .with_label(Label::new((module_id, range.into())).with_message("here"))
+            .finish(),
+        ));
+    }
+    if let Some(error) = errors.into_iter().next() {
+        return Err(Box::new(error));
+    }
+
+    let mut formatter = Formatter::default();
+    formatter.root(&root);
+    Ok(formatter.out)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Sep {
+    NoSpace,
+    Space,
+    /// A line break that never leaves a blank line
+    Break,
+    /// A line break that keeps one blank line if the source had any
+    Line,
+}
+
+#[derive(Default)]
+struct Formatter {
+    out: String,
+    indent: usize,
+    /// Asked for by the enclosing node, overrides the spacing between tokens
+    pending: Option<Sep>,
+    /// Kind and parent kind of the last written token
+    last: Option<(Token, Token)>,
+    /// Comments run until the end of their line
+    after_comment: bool,
+    /// Newlines in the source since the last written token or comment
+    newlines: usize,
+}
+
+impl Formatter {
+    fn root(&mut self, root: &SyntaxNode) {
+        for child in root.children_with_tokens() {
+            if child.as_node().is_some() {
+                self.sep(Sep::Line);
+            }
+            self.element(child);
+        }
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+    }
+
+    fn element(&mut self, element: SyntaxElement) {
+        match element {
+            NodeOrToken::Node(node) => self.node(&node),
+            NodeOrToken::Token(token) => self.token(&token),
+        }
+    }
+
+    fn node(&mut self, node: &SyntaxNode) {
+        match node.kind() {
+            Token::Block => self.block(node),
+            Token::Struct | Token::StructType => self.fields(node),
+            _ => node
+                .children_with_tokens()
+                .for_each(|child| self.element(child)),
+        }
+    }
+
+    /// Every statement gets its own line, empty blocks stay `{}`.
+    fn block(&mut self, block: &SyntaxNode) {
+        let mut empty = true;
+        for child in block.children_with_tokens() {
+            match child.kind() {
+                Token::LBRACE => {
+                    self.element(child);
+                    self.indent += 1;
+                }
+                Token::RBRACE => {
+                    self.indent -= 1;
+                    self.sep(if empty { Sep::NoSpace } else { Sep::Break });
+                    self.element(child);
+                }
+                _ => {
+                    if child.as_node().is_some() {
+                        self.sep(Sep::Line);
+                        empty = false;
+                    }
+                    self.element(child);
+                }
+            }
+        }
+    }
+
+    /// Struct literals and definitions stay on one line, unless they already
+    /// span several. Then every field gets its own line and a trailing comma.
+    fn fields(&mut self, node: &SyntaxNode) {
+        let multiline = node.text().contains_char('\n')
+            || node.descendants().any(|descendant| {
+                descendant.kind() == Token::Block && descendant.first_child().is_some()
+            });
+        let children: Vec<_> = node.children_with_tokens().collect();
+        let last_field = children
+            .iter()
+            .rposition(|child| !is_trivia(child.kind()) && child.kind() != Token::RBRACE);
+
+        let mut field_start = false;
+        for (i, child) in children.into_iter().enumerate() {
+            let kind = child.kind();
+            match kind {
+                _ if is_trivia(kind) => {
+                    self.element(child);
+                    continue;
+                }
+                Token::LBRACE => {
+                    self.element(child);
+                    self.indent += 1;
+                    field_start = true;
+                    continue;
+                }
+                Token::RBRACE => {
+                    self.indent -= 1;
+                    self.sep(match self.last {
+                        Some((Token::LBRACE, _)) => Sep::NoSpace,
+                        _ if multiline => Sep::Break,
+                        _ => Sep::Space,
+                    });
+                    self.element(child);
+                    continue;
+                }
+                _ => {}
+            }
+
+            if field_start {
+                self.sep(if multiline { Sep::Line } else { Sep::Space });
+                field_start = false;
+            }
+            if kind == Token::COMMA {
+                field_start = true;
+                if !multiline && Some(i) == last_field {
+                    continue;
+                }
+            }
+            self.element(child);
+            if multiline && Some(i) == last_field && kind != Token::COMMA {
+                self.write(Token::COMMA, node.kind(), ",");
+            }
+        }
+    }
+
+    fn token(&mut self, token: &SyntaxToken) {
+        match token.kind() {
+            Token::WHITE_SPACE => {}
+            Token::NEW_LINE => self.newlines += token.text().len(),
+            Token::COMMENT => self.comment(token.text().trim_end()),
+            kind => {
+                let parent = token.parent().map_or(Token::Root, |parent| parent.kind());
+                self.write(kind, parent, token.text());
+            }
+        }
+    }
+
+    fn comment(&mut self, text: &str) {
+        let trailing = self.newlines == 0 && !self.after_comment;
+        self.write_sep(if trailing { Sep::Space } else { Sep::Line });
+        self.out.push_str(text);
+        self.after_comment = true;
+        self.newlines = 1;
+    }
+
+    fn sep(&mut self, sep: Sep) {
+        self.pending = Some(self.pending.map_or(sep, |pending| pending.max(sep)));
+    }
+
+    fn write(&mut self, kind: Token, parent: Token, text: &str) {
+        let mut sep = self
+            .pending
+            .take()
+            .unwrap_or_else(|| default_sep(self.last, kind, parent));
+        if self.after_comment {
+            sep = sep.max(Sep::Break);
+        }
+        self.write_sep(sep);
+        self.out.push_str(text);
+        self.last = Some((kind, parent));
+        self.after_comment = false;
+        self.newlines = 0;
+    }
+
+    fn write_sep(&mut self, sep: Sep) {
+        if self.out.is_empty() {
+            return;
+        }
+        match sep {
+            Sep::NoSpace => {}
+            Sep::Space => self.out.push(' '),
+            Sep::Break | Sep::Line => {
+                let after_brace = matches!(self.last, Some((Token::LBRACE, _)));
+                if sep == Sep::Line && self.newlines > 1 && !after_brace {
+                    self.out.push('\n');
+                }
+                self.out.push('\n');
+                self.out.push_str(&INDENT.repeat(self.indent));
+            }
+        }
+    }
+}
+
+fn is_trivia(kind: Token) -> bool {
+    matches!(kind, Token::WHITE_SPACE | Token::NEW_LINE | Token::COMMENT)
+}
+
+/// Spacing between two tokens of an expression.
+fn default_sep(last: Option<(Token, Token)>, kind: Token, parent: Token) -> Sep {
+    let Some((last, last_parent)) = last else {
+        return Sep::NoSpace;
+    };
+    match (last, kind) {
+        (
+            _,
+            Token::COMMA
+            | Token::SEMICOLON
+            | Token::COLON
+            | Token::DOT
+            | Token::COLONCOLON
+            | Token::RPAREN
+            | Token::RBRACKET,
+        ) => Sep::NoSpace,
+        (Token::LPAREN | Token::LBRACKET | Token::DOT | Token::COLONCOLON, _) => Sep::NoSpace,
+        (Token::LBRACE, _) if last_parent == Token::ImportList => Sep::NoSpace,
+        (_, Token::RBRACE) if parent == Token::ImportList => Sep::NoSpace,
+        (_, Token::LPAREN) if parent == Token::Call => Sep::NoSpace,
+        (_, Token::LBRACKET) if parent == Token::ComponentAccess => Sep::NoSpace,
+        (op, _) if last_parent == Token::Prefix && op.is_prefix_op() => Sep::NoSpace,
+        _ => Sep::Space,
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,134 @@
+use expect_test::{expect, Expect};
+
+use crate::cst::{self, SyntaxNode, Token};
+
+/// Everything the formatter isn't allowed to change.
+fn tokens(source: &str) -> Vec<(Token, String)> {
+    let (green, _) = cst::run(source, 0).unwrap();
+    SyntaxNode::new_root(green)
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| {
+            !matches!(
+                token.kind(),
+                Token::WHITE_SPACE | Token::NEW_LINE | Token::COMMENT | Token::COMMA
+            )
+        })
+        .map(|token| (token.kind(), token.text().to_string()))
+        .collect()
+}
+
+fn check(source: &str, expect: Expect) {
+    let formatted = super::run(source, 0).unwrap();
+    expect.assert_eq(&formatted);
+    assert_eq!(super::run(&formatted, 0).unwrap(), formatted);
+    assert_eq!(tokens(&formatted), tokens(source));
+}
+
+#[test]
+fn test1() {
+    check(
+        "use std
+   sys   move_right{query entity{entity[ Transform ].translation.x+=-1*( 2+3 );
+   print( entity ,[Health] )}}",
+        expect![[r#"
+            use std
+            sys move_right {
+                query entity {
+                    entity[Transform].translation.x += -1 * (2 + 3);
+                    print(entity, [Health])
+                }
+            }
+        "#]],
+    );
+}
+
+#[test]
+fn test2() {
+    check(
+        "# Components
+comp Velocity: {x: f32,y: f32,}
+comp Spring: {
+  stiffness: f32, damping: f32 }
+
+
+res Health: f32 # remaining
+
+
+sys {
+    # before
+    let a = Vec { x: 1, y: 2, };
+    let b = Vec {
+      x: 1, y: 2 };
+
+    if a.x == 1 { del a } else {}
+    while !(x < 2) {
+        print(\"looping\") # trailing
+        ;
+    }
+    # at the end
+}
+",
+        expect![[r#"
+            # Components
+            comp Velocity: { x: f32, y: f32 }
+            comp Spring: {
+                stiffness: f32,
+                damping: f32,
+            }
+
+            res Health: f32 # remaining
+
+            sys {
+                # before
+                let a = Vec { x: 1, y: 2 };
+                let b = Vec {
+                    x: 1,
+                    y: 2,
+                };
+
+                if a.x == 1 {
+                    del a
+                } else {}
+                while !(x < 2) {
+                    print("looping") # trailing
+                    ;
+                }
+                # at the end
+            }
+        "#]],
+    );
+}
+
+#[test]
+fn test3() {
+    check(
+        "test \"moves\" { let e = spawn(); assert(e == e, \"same\") }",
+        expect![[r#"
+            test "moves" {
+                let e = spawn();
+                assert(e == e, "same")
+            }
+        "#]],
+    );
+}
+
+#[test]
+fn imports() {
+    check(
+        "use std::{ print,Vec  as V }\nuse physics   as phys\nsys { let a = phys::V {x: 1}; }",
+        expect![[r#"
+            use std::{print, Vec as V}
+            use physics as phys
+            sys {
+                let a = phys::V { x: 1 };
+            }
+        "#]],
+    );
+}
+
+#[test]
+fn errors() {
+    assert!(super::run("sys { } ? comp", 0).is_err());
+    assert!(super::run("sys { 1 +  }", 0).is_err());
+}
@@ -1,6 +1,7 @@
 use crate::cst::{StorkLang, SyntaxNodePtr};
 use crate::passes::type_resolution::ResolvedType;
 use crate::{module_index::ModuleID, passes::borrow_resolution::ResolvedEffects};
+use serde::{Deserialize, Serialize};
 use std::any::Any;
 use std::fmt::Debug;
 
@@ -54,9 +55,10 @@ impl Debug for GlobalIdx {
 
 pub enum Node {
     System(System),
+    Test(Test),
     Resource(Resource),
     Component(Component),
-    Import(String),
+    Import(Import),
     TypeIdent(TypeIdent),
     Struct(StructType),
     Expr(Expr),
@@ -64,10 +66,28 @@ pub enum Node {
         identifier: Identifier,
         r#type: ResolvedType,
         effects: ResolvedEffects,
+        info: BuiltinInfo,
         data: Box<dyn Any + Send + Sync>,
     },
 }
 
+/// What tools know about a builtin without its data, which a schema records
+/// along with its name and type.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BuiltinInfo {
+    /// Like `spawn`, which needs a world to itself, so only tests can use it
+    #[serde(default)]
+    pub test_only: bool,
+    /// A type registered as a component, it can also be a resource
+    #[serde(default)]
+    pub component: bool,
+    #[serde(default)]
+    pub resource: bool,
+    /// The parameter names of a function, `None` where there isn't one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub param_names: Vec<Option<String>>,
+}
+
 impl From<Expr> for Node {
     fn from(value: Expr) -> Self {
         Self::Expr(value)
@@ -82,6 +102,25 @@ impl Node {
             None
         }
     }
+
+    /// The name a top level item is declared with, if it has one.
+    pub fn item_identifier(&self) -> Option<Identifier> {
+        match self {
+            Node::Component(typed_ident) | Node::Resource(typed_ident) => {
+                Some(typed_ident.identifier())
+            }
+            Node::Builtin { identifier, .. } => Some(identifier.clone()),
+            Node::System(System {
+                ident: Some(ident), ..
+            }) => Some(Identifier::Name(ident.clone())),
+            Node::System(_)
+            | Node::Test(_)
+            | Node::TypeIdent(_)
+            | Node::Struct(_)
+            | Node::Expr(_)
+            | Node::Import(_) => None,
+        }
+    }
 }
 
 pub type Resource = TypedIdent;
@@ -99,6 +138,30 @@ impl TypedIdent {
     }
 }
 
+/// `use path`, which can also name a single item, like `use std::print`.
+#[derive(Debug, Clone)]
+pub struct Import {
+    /// Segments joined with `::`, like module paths
+    pub path: String,
+    pub kind: ImportKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum ImportKind {
+    /// Every top level name
+    All,
+    /// `use path as alias`, where `alias::name` refers to the items
+    Alias(String),
+    /// `use path::{name, name as alias}`
+    Names(Vec<ImportName>),
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportName {
+    pub name: String,
+    pub alias: Option<String>,
+}
+
 pub struct TypeIdent(pub String);
 pub struct StructType(pub Vec<TypedIdent>);
 
@@ -107,11 +170,17 @@ pub struct System {
     pub block: Idx,
 }
 
+pub struct Test {
+    pub name: String,
+    pub block: Idx,
+}
+
 #[derive(Debug)]
 pub enum Expr {
     Block(Vec<Idx>),
     Identifier(Identifier),
     Number(f32),
+    String(String),
     ComponentAccess {
         entity: Idx,
         component: Idx,
@@ -158,10 +227,15 @@ pub enum Expr {
     Poison,
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub enum Identifier {
     Name(String),
     Operator(Operator),
+    /// `module::name`, where `module` is a module path or starts with an alias
+    Qualified {
+        module: String,
+        name: String,
+    },
 }
 
 impl From<Operator> for Identifier {
@@ -176,7 +250,7 @@ impl<T: Into<String>> From<T> for Identifier {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Operator {
     Add,
     Sub,
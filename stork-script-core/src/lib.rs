@@ -2,12 +2,17 @@
 pub mod ast;
 #[path = "cst/cst.rs"]
 pub mod cst;
+#[path = "diagnostic/diagnostic.rs"]
+pub mod diagnostic;
+#[path = "format/format.rs"]
+pub mod format;
 pub mod hir;
 #[path = "module_index/module_index.rs"]
 pub mod module_index;
 #[path = "passes/passes.rs"]
 pub mod passes;
 pub mod report;
+pub mod schema;
 
 // [X] Add resources
 // [X] Handling multiple items
@@ -38,6 +38,10 @@ impl<V, K: std::cmp::Eq + std::hash::Hash> GlobalMap<V, K> {
     pub fn get_mut(&mut self, idx: impl Into<K>) -> Option<&mut V> {
         self.0.get_mut(&idx.into())
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.0.iter()
+    }
 }
 
 impl<V> GlobalMap<Vec<V>, ModuleID> {
@@ -97,12 +101,48 @@ pub type NameMap = GlobalMap<ResolvedDefinition>;
 pub type TypeMap = DenseGlobalMap<ResolvedType>;
 // TODO: put types into their own map, and just put the ids here (so you don't duplicate them)
 pub type EffectMap = GlobalMap<ResolvedEffects>;
+/// The names each block declares, the rest is in its parents
+pub type ScopeMap = GlobalMap<Scope>;
+/// The names visible anywhere in a module, its items and imports
+pub type ModuleScopeMap = GlobalMap<HashMap<Identifier, ResolvedDefinition>, ModuleID>;
 pub type ErrorMap = GlobalMap<Vec<Report>, ModuleID>;
 
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    /// The block around this one, `None` for the outermost block of an item
+    pub parent: Option<GlobalIdx>,
+    pub names: HashMap<Identifier, ResolvedDefinition>,
+}
+
 #[derive(Default)]
 pub struct Cache {
     pub errors: ErrorMap,
     pub names: NameMap,
     pub types: TypeMap,
     pub effects: EffectMap,
+    pub scopes: ScopeMap,
+    pub module_scopes: ModuleScopeMap,
+}
+
+impl Cache {
+    /// Every name visible at the end of `block`, inner blocks shadowing outer
+    /// ones, which shadow the module's names.
+    pub fn visible_names(&self, block: GlobalIdx) -> HashMap<Identifier, ResolvedDefinition> {
+        let mut blocks = Vec::new();
+        let mut next = Some(block);
+        while let Some(scope) = next.and_then(|block| self.scopes.get_ref(block)) {
+            blocks.push(scope);
+            next = scope.parent;
+        }
+        let mut names = self.module_scopes.get(block.module()).unwrap_or_default();
+        for scope in blocks.into_iter().rev() {
+            names.extend(
+                scope
+                    .names
+                    .iter()
+                    .map(|(name, definition)| (name.clone(), *definition)),
+            );
+        }
+        names
+    }
 }
@@ -1,14 +1,19 @@
 pub mod cache;
 
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Component, Path},
+};
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use ariadne::Source;
+use rowan::GreenNode;
 
 use crate::{
+    diagnostic::Diagnostic,
     hir::*,
     passes::{self},
-    report::Report,
+    report::{self, Report},
 };
 
 use cache::Cache;
@@ -25,12 +30,23 @@ impl ModuleIndex {
         path: impl Into<String>,
         f: impl FnOnce(ModuleID) -> anyhow::Result<Module>,
     ) -> anyhow::Result<()> {
+        let path = path.into();
+        if self.modules.paths.contains_key(&path) {
+            bail!("There already is a module `{path}`");
+        }
         let id = self.modules.modules.len();
-        self.modules.paths.insert(path.into(), id);
         self.modules.modules.push(f(id)?);
+        self.modules.paths.insert(path, id);
         Ok(())
     }
 
+    /// Swaps in a new version of a module, like after an edit, keeping its
+    /// path and id. The index has to be compiled again.
+    #[track_caller]
+    pub fn replace_module(&mut self, module_id: ModuleID, module: Module) {
+        self.modules.modules[module_id] = module;
+    }
+
     pub fn compile(&mut self) -> anyhow::Result<()> {
         self.cache = Cache::default();
 
@@ -43,29 +59,47 @@ impl ModuleIndex {
         for module_id in self.modules.all_ids() {
             passes::borrow_resolution::run(&mut self.cache, &self.modules, module_id);
         }
+        for module_id in self.modules.all_ids() {
+            passes::lint::run(&mut self.cache, &self.modules, module_id);
+        }
         Ok(())
     }
 
     pub fn print_errors(&self) {
         for module_id in self.modules.all_ids() {
-            for error in &self.modules.get_ref(module_id).parser_errors {
-                error.print(&self.modules).unwrap();
-            }
-            for error in self.cache.errors.get_ref(module_id).unwrap_or(&Vec::new()) {
+            for error in self.reports(module_id) {
                 error.print(&self.modules).unwrap();
             }
         }
     }
 
+    /// Every report of every module, in a serializable form.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.modules
+            .all_ids()
+            .flat_map(|module_id| self.reports(module_id))
+            .map(|report| Diagnostic::new(report, &self.modules))
+            .collect()
+    }
+
+    pub fn diagnostics_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&self.diagnostics())
+    }
+
+    /// Whether there are reports other than warnings.
     pub fn has_errors(&self) -> bool {
-        self.modules.all_ids().any(|module_id| {
-            !self.modules.get_ref(module_id).parser_errors.is_empty()
-                || self
-                    .cache
-                    .errors
-                    .get_ref(module_id)
-                    .is_some_and(|errors| !errors.is_empty())
-        })
+        self.modules
+            .all_ids()
+            .any(|module_id| self.reports(module_id).any(Report::is_error))
+    }
+
+    /// Parser errors followed by errors from the passes.
+    pub fn reports(&self, module_id: ModuleID) -> impl Iterator<Item = &Report> {
+        self.modules
+            .get_ref(module_id)
+            .parser_errors
+            .iter()
+            .chain(self.cache.errors.get_ref(module_id).into_iter().flatten())
     }
 }
 
@@ -96,8 +130,16 @@ impl ModuleCollection {
         0..self.modules.len()
     }
 
-    pub fn path_to_id(&self, path: &str) -> ModuleID {
-        self.paths[path]
+    pub fn get_id(&self, path: &str) -> Option<ModuleID> {
+        self.paths.get(path).copied()
+    }
+
+    #[track_caller]
+    pub fn id_to_path(&self, module_id: ModuleID) -> &str {
+        self.paths
+            .iter()
+            .find_map(|(path, id)| (*id == module_id).then_some(path.as_str()))
+            .unwrap()
     }
 
     pub fn top_level_ids(&self, module_id: ModuleID) -> impl Iterator<Item = GlobalIdx> + Clone {
@@ -109,6 +151,23 @@ impl ModuleCollection {
     pub fn top_level_names(&self, module_id: ModuleID) -> HashMap<Identifier, Idx> {
         self.get_ref(module_id).top_level_names()
     }
+
+    pub fn public_names(&self, module_id: ModuleID) -> HashMap<Identifier, Idx> {
+        self.get_ref(module_id).public_names()
+    }
+
+    pub fn is_public(&self, idx: GlobalIdx) -> bool {
+        self.get_ref(idx.module()).is_public(idx.idx())
+    }
+
+    /// The modules imported by `module_id` that exist.
+    pub fn imports(&self, module_id: ModuleID) -> impl Iterator<Item = ModuleID> + '_ {
+        self.top_level_ids(module_id)
+            .filter_map(|item| match self.get_node(item) {
+                Node::Import(import) => self.get_id(&import.path),
+                _ => None,
+            })
+    }
 }
 
 impl ariadne::Cache<ModuleID> for &ModuleCollection {
@@ -122,12 +181,7 @@ impl ariadne::Cache<ModuleID> for &ModuleCollection {
     }
 
     fn display<'a>(&self, id: &'a ModuleID) -> Option<Box<dyn std::fmt::Display + 'a>> {
-        Some(Box::new(
-            self.paths
-                .iter()
-                .find_map(|(path, module_id)| (id == module_id).then(|| path.clone()))
-                .unwrap(),
-        ))
+        Some(Box::new(self.id_to_path(*id).to_string()))
     }
 }
 
@@ -136,21 +190,38 @@ pub struct Module {
     pub nodes: Arena,
     pub spans: SpanMap,
     pub top_level: Vec<Idx>,
+    /// Top level items declared `pub`
+    pub public: HashSet<Idx>,
     pub parser_errors: Vec<Report>,
 }
 
 impl Module {
     pub fn from_source(source: &str, module_id: ModuleID) -> anyhow::Result<Self> {
-        let (cst, errors) = crate::cst::run(source, module_id).map_err(|err| anyhow!("{err:?}"))?;
-        let ast = crate::ast::run(cst, module_id).map_err(|err| anyhow!("{err:?}"))?;
-        crate::passes::lower::run(ast, source.to_string().into(), module_id, errors)
+        Self::parse(source, module_id)
+            .map(|(module, _)| module)
             .map_err(|err| anyhow!("{err:?}"))
     }
 
+    /// Like `from_source`, but also returns the syntax tree, and keeps the
+    /// report of why the source couldn't be parsed at all.
+    pub fn parse(source: &str, module_id: ModuleID) -> report::Result<(Self, GreenNode)> {
+        let (cst, errors) = crate::cst::run(source, module_id)?;
+        let ast = crate::ast::run(cst.clone(), module_id)?;
+        let module = crate::passes::lower::run(ast, source.to_string().into(), module_id, errors)?;
+        Ok((module, cst))
+    }
+
     pub fn alloc_top_level(&mut self, node: Node) {
         let id = self.nodes.alloc(node);
         self.top_level.push(id);
     }
+
+    /// Zero-indexed line and byte column where the node starts.
+    pub fn line_col(&self, idx: Idx) -> Option<(usize, usize)> {
+        let offset = self.spans.get(idx)?.text_range().start().into();
+        let (_, line, col) = self.source.get_byte_line(offset)?;
+        Some((line, col))
+    }
 }
 
 impl Module {
@@ -158,29 +229,45 @@ impl Module {
         self.top_level.clone().into_iter()
     }
 
+    /// If a name is declared more than once the first one wins, the others
+    /// are reported by name resolution.
     pub fn top_level_names(&self) -> HashMap<Identifier, Idx> {
-        self.top_level_ids()
-            .filter_map(|id| {
-                let ident = match &self.nodes[id] {
-                    Node::Component(typed_ident) | Node::Resource(typed_ident) => {
-                        typed_ident.identifier()
-                    }
-                    Node::Builtin { identifier, .. } => identifier.clone(),
-                    Node::System(System {
-                        ident: Some(ident), ..
-                    }) => Identifier::Name(ident.clone()),
-                    Node::System(_)
-                    | Node::TypeIdent(_)
-                    | Node::Struct(_)
-                    | Node::Expr(_)
-                    | Node::Import(_) => {
-                        return None;
-                    }
-                };
-                Some((ident, id))
-            })
-            .collect()
+        let mut names = HashMap::new();
+        for id in self.top_level_ids() {
+            if let Some(ident) = self.nodes[id].item_identifier() {
+                names.entry(ident).or_insert(id);
+            }
+        }
+        names
+    }
+
+    /// Whether other modules can use the item. Builtins always can.
+    pub fn is_public(&self, idx: Idx) -> bool {
+        matches!(self.nodes[idx], Node::Builtin { .. }) || self.public.contains(&idx)
+    }
+
+    /// The names other modules can use, see [`Module::top_level_names`].
+    pub fn public_names(&self) -> HashMap<Identifier, Idx> {
+        let mut names = self.top_level_names();
+        names.retain(|_, idx| self.is_public(*idx));
+        names
     }
 }
 
 pub type ModuleID = usize;
+
+/// The path of the module in `file`, named after where it is in `root`, like
+/// `game::physics` for `game/physics.strk`. `None` if `file` isn't in `root`.
+pub fn module_path(root: &Path, file: &Path) -> Option<String> {
+    let relative = file.strip_prefix(root).ok()?;
+    let mut segments = Vec::new();
+    for component in relative.parent()?.components() {
+        match component {
+            Component::Normal(directory) => segments.push(directory.to_str()?),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    segments.push(relative.file_stem()?.to_str()?);
+    Some(segments.join("::"))
+}
@@ -47,6 +47,7 @@ impl ResolveCtx<'_> {
     fn node_inner(&mut self, node: GlobalIdx, ctx: AccessRequirement) -> Option<ResolvedEffects> {
         Some(match self.modules.get_node(node) {
             Node::System(system) => return self.node((node.module(), system.block), ctx),
+            Node::Test(test) => return self.node((node.module(), test.block), ctx),
             Node::Resource(_)
             | Node::Component(_)
             | Node::TypeIdent(_)
@@ -106,7 +107,7 @@ impl ResolveCtx<'_> {
                     .map(|expr| self.node((id, expr), arg_access))
                     .fold(effect, join)
             }
-            Expr::Identifier(_) | Expr::Number(_) | Expr::Poison => {
+            Expr::Identifier(_) | Expr::Number(_) | Expr::String(_) | Expr::Poison => {
                 Some(ResolvedEffects::default())
             }
             Expr::ComponentAccess { component, entity } => {
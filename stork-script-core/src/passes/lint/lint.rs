@@ -0,0 +1,309 @@
+//! Warnings about code that compiles but is likely a mistake. A lint can be
+//! silenced for an item with a `#[allow(lint)]` comment right above it.
+
+use std::{collections::HashSet, ops::Range};
+
+use crate::{
+    diagnostic::Code,
+    hir::*,
+    module_index::{
+        cache::{Cache, ErrorMap},
+        ModuleCollection, ModuleID,
+    },
+    report::{Edit, Fix, Label, Report, ReportBuilder, ReportKind},
+};
+
+pub fn run(cache: &mut Cache, modules: &ModuleCollection, module_id: ModuleID) {
+    let used = cache
+        .names
+        .iter()
+        .map(|(_, definition)| definition.definition())
+        .collect();
+    let used_here: HashSet<_> = cache
+        .names
+        .iter()
+        .filter(|(node, _)| node.module() == module_id)
+        .map(|(_, definition)| definition.definition())
+        .collect();
+    let used_modules = used_here
+        .iter()
+        .map(|definition| definition.module())
+        .collect();
+    let builtins = modules
+        .get_id("std")
+        .map(|std| modules.top_level_names(std).into_keys().collect())
+        .unwrap_or_default();
+
+    let mut ctx = LintCtx {
+        errors: &mut cache.errors,
+        modules,
+        module_id,
+        used,
+        used_here,
+        used_modules,
+        builtins,
+        allowed: Vec::new(),
+    };
+    for item in modules.top_level_ids(module_id) {
+        if let Some(span) = ctx.span(item) {
+            let allowed = allowed_lints(modules.get_ref(module_id).source.text(), span.start);
+            ctx.allowed.push((span, allowed));
+        }
+    }
+
+    for item in modules.top_level_ids(module_id) {
+        match modules.get_node(item) {
+            Node::Import(import) => ctx.import(item, import),
+            Node::Component(typed_ident) | Node::Resource(typed_ident) => {
+                ctx.item(item, &typed_ident.ident)
+            }
+            _ => {}
+        }
+    }
+    for (idx, node) in modules.get_ref(module_id).nodes.iter() {
+        match node {
+            Node::Expr(Expr::Let { lvalue, .. }) => {
+                let lvalue = GlobalIdx::new(module_id, *lvalue);
+                if let Some(Identifier::Name(name)) = modules.get_node(lvalue).as_expr_identifier()
+                {
+                    ctx.variable(lvalue, name);
+                    ctx.shadowed_builtin(lvalue, name);
+                }
+            }
+            Node::Expr(Expr::Query { entity, .. }) => {
+                ctx.shadowed_builtin(GlobalIdx::new(module_id, idx), entity)
+            }
+            _ => {}
+        }
+    }
+}
+
+struct LintCtx<'c> {
+    errors: &'c mut ErrorMap,
+    modules: &'c ModuleCollection,
+    module_id: ModuleID,
+    /// Definitions that any module refers to
+    used: HashSet<GlobalIdx>,
+    /// Definitions that this module refers to
+    used_here: HashSet<GlobalIdx>,
+    /// Modules that this module refers to
+    used_modules: HashSet<ModuleID>,
+    builtins: HashSet<Identifier>,
+    /// The span of each item and the lints allowed in it
+    allowed: Vec<(Range<usize>, Vec<String>)>,
+}
+
+impl LintCtx<'_> {
+    fn variable(&mut self, node: GlobalIdx, name: &str) {
+        if self.used.contains(&node) || name.starts_with('_') {
+            return;
+        }
+        self.warn(Code::UnusedVariable, node, |report| {
+            report
+                .with_message(format!("Unused variable `{name}`"))
+                .with_help(format!("if this is intended, call it `_{name}`"))
+        });
+    }
+
+    fn import(&mut self, node: GlobalIdx, import: &Import) {
+        let used = match (&import.kind, self.modules.get_id(&import.path)) {
+            (ImportKind::Names(names), Some(module_id)) => {
+                return self.import_names(node, import, module_id, names)
+            }
+            (_, Some(module_id)) => self.used_modules.contains(&module_id),
+            // `use std::print` imports a single item
+            (_, None) => {
+                let Some(definition) = import
+                    .path
+                    .rsplit_once("::")
+                    .and_then(|(path, name)| self.imported(self.modules.get_id(path)?, name))
+                else {
+                    return;
+                };
+                self.used_here.contains(&definition)
+            }
+        };
+        if !used {
+            self.unused_import(node, &import.path);
+        }
+    }
+
+    /// Names of a `use path::{a, b as c}` are unused on their own, the whole
+    /// import only if none of them is used.
+    fn import_names(
+        &mut self,
+        node: GlobalIdx,
+        import: &Import,
+        module_id: ModuleID,
+        names: &[ImportName],
+    ) {
+        let unused: Vec<_> = names
+            .iter()
+            .filter(|name| {
+                self.imported(module_id, &name.name)
+                    .is_some_and(|definition| !self.used_here.contains(&definition))
+            })
+            .collect();
+        if unused.len() == names.len() {
+            return self.unused_import(node, &import.path);
+        }
+        let Some(span) = self.span(node) else {
+            return;
+        };
+        let modules = self.modules;
+        let text = modules.get_ref(self.module_id).source.text();
+        let entries = import_list(text, span);
+        for name in unused {
+            let shown = name.alias.as_ref().unwrap_or(&name.name);
+            let Some(index) = entries.iter().position(|entry| {
+                let mut words = text[entry.clone()].split_whitespace();
+                words.next() == Some(name.name.as_str()) && words.last() == Some(shown.as_str())
+            }) else {
+                continue;
+            };
+            // Along with the comma after it, or before it for the last one
+            let removed = match entries.get(index + 1) {
+                Some(next) => entries[index].start..next.start,
+                None if index > 0 => entries[index - 1].end..entries[index].end,
+                None => entries[index].clone(),
+            };
+            let fix = Fix::new(
+                format!("Remove `{shown}` from the import"),
+                vec![Edit::new((self.module_id, removed), "")],
+            );
+            self.warn_at(Code::UnusedImport, entries[index].clone(), |report| {
+                report
+                    .with_message(format!("Unused import `{shown}`"))
+                    .with_fix(fix)
+            });
+        }
+    }
+
+    /// The top level item `name` of `module_id`.
+    fn imported(&self, module_id: ModuleID, name: &str) -> Option<GlobalIdx> {
+        let idx = self
+            .modules
+            .top_level_names(module_id)
+            .get(&Identifier::Name(name.to_string()))
+            .copied()?;
+        Some(GlobalIdx::new(module_id, idx))
+    }
+
+    fn unused_import(&mut self, node: GlobalIdx, path: &str) {
+        let fix = self.span(node).map(|span| {
+            // Along with its line break
+            let text = self.modules.get_ref(self.module_id).source.text();
+            let end = span.end + usize::from(text[span.end..].starts_with('\n'));
+            Fix::new(
+                format!("Remove `use {path}`"),
+                vec![Edit::new((self.module_id, span.start..end), "")],
+            )
+        });
+        self.warn(Code::UnusedImport, node, |mut report| {
+            report = report.with_message(format!("Unused import `{path}`"));
+            if let Some(fix) = fix {
+                report = report.with_fix(fix);
+            }
+            report
+        });
+    }
+
+    fn item(&mut self, node: GlobalIdx, name: &str) {
+        // `pub` items can be used by modules that aren't checked with this one
+        if self.used.contains(&node) || self.modules.is_public(node) {
+            return;
+        }
+        let kind = match self.modules.get_node(node) {
+            Node::Resource(_) => "Resource",
+            _ => "Component",
+        };
+        self.warn(Code::UnusedItem, node, |report| {
+            report.with_message(format!("{kind} `{name}` is never accessed"))
+        });
+    }
+
+    fn shadowed_builtin(&mut self, node: GlobalIdx, name: &str) {
+        if !self.builtins.contains(&Identifier::Name(name.to_string())) {
+            return;
+        }
+        self.warn(Code::ShadowedBuiltin, node, |report| {
+            report
+                .with_message(format!("`{name}` shadows a builtin from `std`"))
+                .with_help(format!("`{name}` from `std` can't be used in its scope"))
+        });
+    }
+
+    /// Reports `code` about `node` unless its item allows it.
+    fn warn(
+        &mut self,
+        code: Code,
+        node: GlobalIdx,
+        f: impl FnOnce(ReportBuilder) -> ReportBuilder,
+    ) {
+        if let Some(span) = self.span(node) {
+            self.warn_at(code, span, f);
+        }
+    }
+
+    fn warn_at(
+        &mut self,
+        code: Code,
+        span: Range<usize>,
+        f: impl FnOnce(ReportBuilder) -> ReportBuilder,
+    ) {
+        let lint = code.lint().unwrap_or_default();
+        let allowed = self.allowed.iter().any(|(item, allowed)| {
+            item.start <= span.start
+                && span.end <= item.end
+                && allowed.iter().any(|allowed| allowed == lint)
+        });
+        if allowed {
+            return;
+        }
+        let report = Report::build(ReportKind::Warning, code, self.module_id, span.start)
+            .with_label(Label::new((self.module_id, span)).with_message("here"))
+            .with_note(format!("`#[allow({lint})]` above the item silences this"));
+        self.errors.push(self.module_id, f(report).finish());
+    }
+
+    fn span(&self, node: GlobalIdx) -> Option<Range<usize>> {
+        let ptr = self.modules.get_ref(node.module()).spans.get(node.idx())?;
+        Some(ptr.text_range().into())
+    }
+}
+
+/// The span of each `name` or `name as alias` in the `{..}` of the import at `span`.
+fn import_list(text: &str, span: Range<usize>) -> Vec<Range<usize>> {
+    let import = &text[span.clone()];
+    let Some(open) = import.find('{') else {
+        return Vec::new();
+    };
+    let close = import.rfind('}').unwrap_or(import.len());
+    let mut start = span.start + open + 1;
+    let mut entries = Vec::new();
+    for entry in import[open + 1..close].split(',') {
+        let trimmed = entry.trim_start();
+        let entry_start = start + entry.len() - trimmed.len();
+        if !trimmed.trim_end().is_empty() {
+            entries.push(entry_start..entry_start + trimmed.trim_end().len());
+        }
+        start += entry.len() + 1;
+    }
+    entries
+}
+
+/// The lints in `#[allow(a, b)]` comments right above `start`.
+fn allowed_lints(source: &str, start: usize) -> Vec<String> {
+    source[..start]
+        .trim_end_matches([' ', '\t'])
+        .lines()
+        .rev()
+        .map(str::trim)
+        .take_while(|line| line.starts_with('#'))
+        .filter_map(|line| line.strip_prefix("#[allow(")?.strip_suffix(")]"))
+        .flat_map(|lints| lints.split(',').map(|lint| lint.trim().to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,122 @@
+use crate::{
+    module_index::{Module, ModuleIndex},
+    passes::type_resolution::InnerResolvedType,
+    report::Report,
+    schema::{Builtin, Schema, SCHEMA_VERSION},
+};
+
+/// The reports of `main`, which can use `physics` and a `std` with `print`.
+fn reports(main: &str) -> Vec<Report> {
+    let mut index = ModuleIndex::default();
+    index
+        .add_module("main", |module_id| Module::from_source(main, module_id))
+        .unwrap();
+    index
+        .add_module("physics", |module_id| {
+            Module::from_source("pub comp Velocity: f32\npub comp Mass: f32\n", module_id)
+        })
+        .unwrap();
+    let std = Schema {
+        version: SCHEMA_VERSION,
+        builtins: vec![Builtin {
+            identifier: "print".into(),
+            r#type: InnerResolvedType::Function {
+                params: vec![InnerResolvedType::F32],
+                ret: Box::new(InnerResolvedType::Unit),
+            },
+            info: Default::default(),
+        }],
+    };
+    index.add_module("std", |_| Ok(std.to_module())).unwrap();
+    index.compile().unwrap();
+    index.reports(0).cloned().collect()
+}
+
+fn check(main: &str, expected: &[(&str, &str)]) {
+    let reports = reports(main);
+    let reports = reports
+        .iter()
+        .map(|report| {
+            (
+                report.code.as_str(),
+                report.message.as_deref().unwrap_or_default(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(reports, expected);
+}
+
+#[test]
+fn unused_variables() {
+    check(
+        "sys {\n    let a = 1;\n    let _b = 2;\n}\n",
+        &[("W0001", "Unused variable `a`")],
+    );
+}
+
+#[test]
+fn unused_imports() {
+    check(
+        "use physics\nsys {}\n",
+        &[("W0002", "Unused import `physics`")],
+    );
+    check(
+        "use std::print\nsys {}\n",
+        &[("W0002", "Unused import `std::print`")],
+    );
+    check("use std::print as p\nsys { p(1); }\n", &[]);
+    check(
+        "use std::{print}\nuse physics::{Velocity, Mass as M}\nsys { query e { e[Velocity] = 1; } }\n",
+        &[
+            ("W0002", "Unused import `std`"),
+            ("W0002", "Unused import `M`"),
+        ],
+    );
+}
+
+#[test]
+fn unused_import_names_are_removed_with_their_comma() {
+    let main = "use physics::{Velocity, Mass as M}\nsys { query e { e[M] = 1; } }\n";
+    let reports = reports(main);
+    assert_eq!(reports.len(), 1);
+    let edit = &reports[0].fixes[0].edits[0];
+    let mut fixed = main.to_string();
+    fixed.replace_range(edit.span.1.clone(), &edit.text);
+    assert!(fixed.starts_with("use physics::{Mass as M}\n"));
+
+    let main = "use physics::{Velocity, Mass}\nsys { query e { e[Velocity] = 1; } }\n";
+    let reports = reports(main);
+    let edit = &reports[0].fixes[0].edits[0];
+    let mut fixed = main.to_string();
+    fixed.replace_range(edit.span.1.clone(), &edit.text);
+    assert!(fixed.starts_with("use physics::{Velocity}\n"));
+}
+
+#[test]
+fn unused_items() {
+    check(
+        "comp Unused: f32\npub comp Exported: f32\nres Score: f32\nsys { let _s = [Score]; }\n",
+        &[("W0003", "Component `Unused` is never accessed")],
+    );
+}
+
+#[test]
+fn shadowed_builtins() {
+    check(
+        "sys {\n    let print = 1;\n    let _a = print;\n}\n",
+        &[("W0004", "`print` shadows a builtin from `std`")],
+    );
+}
+
+#[test]
+fn allowed_lints() {
+    check(
+        "#[allow(dead_code)]\ncomp Unused: f32\n#[allow(unused_variables, shadowed_builtins)]\nsys {\n    let print = 1;\n}\n",
+        &[],
+    );
+    // Only for the item right below
+    check(
+        "#[allow(unused_variables)]\nsys {}\nsys {\n    let a = 1;\n}\n",
+        &[("W0001", "Unused variable `a`")],
+    );
+}
@@ -1,6 +1,9 @@
+use std::collections::HashSet;
+
 use ariadne::Source;
 
 use crate::cst::SyntaxNodePtr;
+use crate::diagnostic::Code;
 use crate::hir::*;
 use crate::module_index::{Module, ModuleID};
 use crate::report::{Report, ReportKind, Result};
@@ -18,7 +21,18 @@ pub fn run(
         errors: parse_errors,
         nodes: Default::default(),
     };
-    let top_level = root.items().filter_map(|item| lower.item(item)).collect();
+    let mut public = HashSet::new();
+    let top_level = root
+        .items()
+        .filter_map(|item| {
+            let is_public = item.is_public();
+            let idx = lower.item(item)?;
+            if is_public {
+                public.insert(idx);
+            }
+            Some(idx)
+        })
+        .collect();
     let LowerCtx {
         spans,
         errors,
@@ -31,6 +45,7 @@ pub fn run(
         nodes,
         spans,
         top_level,
+        public,
         parser_errors: errors,
     })
 }
@@ -55,7 +70,32 @@ impl LowerCtx {
                 let typed_ident = self.typed_ident(component.field()?)?;
                 self.alloc(span, Node::Component(typed_ident))
             }
-            ast::Item::Import(import) => self.alloc(span, Node::Import(import.ident()?)),
+            ast::Item::Import(import) => {
+                let kind = if let Some(names) = import.names() {
+                    ImportKind::Names(
+                        names
+                            .into_iter()
+                            .filter_map(|name| {
+                                Some(ImportName {
+                                    name: name.name()?,
+                                    alias: name.alias(),
+                                })
+                            })
+                            .collect(),
+                    )
+                } else if let Some(alias) = import.alias() {
+                    ImportKind::Alias(alias)
+                } else {
+                    ImportKind::All
+                };
+                let path = import.path()?;
+                self.alloc(span, Node::Import(Import { path, kind }))
+            }
+            ast::Item::Test(test) => {
+                let name = test.name()?;
+                let block = self.expr(ast::Expr::Block(test.block()?));
+                self.alloc(span, Node::Test(Test { name, block }))
+            }
         })
     }
 
@@ -84,11 +124,13 @@ impl LowerCtx {
     fn system(&mut self, system: ast::System) -> Option<Idx> {
         let block = self.expr(ast::Expr::Block(system.block()?));
 
-        let id = self.nodes.alloc(Node::System(System {
-            // _span: system.ptr(),
-            ident: system.ident(),
-            block,
-        }));
+        let id = self.alloc(
+            system.ptr(),
+            Node::System(System {
+                ident: system.ident(),
+                block,
+            }),
+        );
 
         Some(id)
     }
@@ -106,10 +148,12 @@ mod expr {
                 ast::Expr::UnaryExpr(unary_expr) => self.unary_expr(unary_expr),
                 ast::Expr::BinaryExpr(binary_expr) => self.binary_expr(binary_expr),
                 ast::Expr::Literal(literal) => {
-                    if let Some(ident) = literal.as_identifier() {
-                        self.alloc(literal.ptr(), Expr::Identifier(Identifier::Name(ident)))
+                    if let Some(path) = literal.as_path() {
+                        self.alloc(literal.ptr(), Expr::Identifier(identifier(path)))
                     } else if let Some(number) = literal.as_number() {
                         self.alloc(literal.ptr(), Expr::Number(number))
+                    } else if let Some(string) = literal.as_string() {
+                        self.alloc(literal.ptr(), Expr::String(string))
                     } else {
                         self.alloc(literal.ptr(), Expr::Poison)
                     }
@@ -172,10 +216,10 @@ mod expr {
                 }
                 ast::Expr::Struct(r#struct) => {
                     let span = r#struct.ptr();
-                    let Some(ident) = r#struct.ident() else {
+                    let Some(path) = r#struct.path() else {
                         return self.alloc_expr_poison();
                     };
-                    let ident = Identifier::Name(ident);
+                    let ident = identifier(path);
                     let fields = r#struct
                         .fields()
                         .map(|(member, expr)| (member, self.expr(expr)))
@@ -305,8 +349,9 @@ mod expr {
 
     impl LowerCtx {
         fn alloc_expr_poison(&mut self) -> Idx {
-            self.errors
-                .push(Report::build(ReportKind::Error, self.module_id, 0).finish());
+            self.errors.push(
+                Report::build(ReportKind::Error, Code::InvalidSyntax, self.module_id, 0).finish(),
+            );
             self.nodes.alloc(Node::Expr(Expr::Poison))
         }
     }
@@ -319,3 +364,16 @@ impl LowerCtx {
         idx
     }
 }
+
+/// `a` or `a::b::c`, which has to have at least one segment.
+fn identifier(mut path: Vec<String>) -> Identifier {
+    let name = path.pop().unwrap_or_default();
+    if path.is_empty() {
+        Identifier::Name(name)
+    } else {
+        Identifier::Qualified {
+            module: path.join("::"),
+            name,
+        }
+    }
+}
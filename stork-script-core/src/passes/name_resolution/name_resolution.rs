@@ -1,16 +1,19 @@
 mod name_scope;
 
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+
 use crate::{
+    diagnostic::Code,
     module_index::cache::{ErrorMap, ResolvedDefinition},
-    report::{Label, Report, ReportBuilder, ReportKind},
+    report::{closest_match, Edit, Fix, Label, Report, ReportBuilder, ReportKind},
 };
 use name_scope::NameScope;
 
 use crate::{
     hir::*,
     module_index::{
-        cache::{Cache, NameMap},
-        ModuleCollection,
+        cache::{Cache, NameMap, Scope, ScopeMap},
+        ModuleCollection, ModuleID,
     },
 };
 
@@ -19,13 +22,19 @@ pub fn run(cache: &mut Cache, modules: &ModuleCollection, module_id: usize) -> a
         errors: &mut cache.errors,
         modules,
         names: &mut cache.names,
+        scopes: &mut cache.scopes,
         scope: NameScope::new(),
+        block: None,
+        aliases: HashMap::new(),
+        private: HashMap::new(),
+        in_test: false,
     };
 
     for node in modules.top_level_ids(module_id) {
         ctx.import(node);
     }
-    ctx.import_module(module_id);
+    ctx.declare_items(module_id);
+    cache.module_scopes.set(module_id, ctx.scope.ident_map());
 
     for node in modules.top_level_ids(module_id) {
         ctx.node(node);
@@ -37,7 +46,17 @@ struct ResolveCtx<'c> {
     modules: &'c ModuleCollection,
     errors: &'c mut ErrorMap,
     names: &'c mut NameMap,
+    scopes: &'c mut ScopeMap,
     scope: NameScope,
+    /// The innermost block being resolved
+    block: Option<GlobalIdx>,
+    /// Modules imported with `use path as alias`
+    aliases: HashMap<String, ModuleID>,
+    /// Names `use module` would import if they were `pub`, to report them
+    /// instead of not finding them
+    private: HashMap<Identifier, GlobalIdx>,
+    /// Whether the node being resolved is in a `test` item
+    in_test: bool,
 }
 
 impl ResolveCtx<'_> {
@@ -47,40 +66,288 @@ impl ResolveCtx<'_> {
             return;
         };
 
-        self.import_module(self.modules.path_to_id(import));
+        let Some(imported) = self.modules.get_id(&import.path) else {
+            // `use std::print` imports a single item
+            let item_import = import
+                .path
+                .rsplit_once("::")
+                .and_then(|(module, name)| Some((self.modules.get_id(module)?, name)));
+            match (item_import, &import.kind) {
+                (Some((module_id, name)), ImportKind::All) => {
+                    self.import_name(item, module_id, name, None)
+                }
+                (Some((module_id, name)), ImportKind::Alias(alias)) => {
+                    self.import_name(item, module_id, name, Some(alias))
+                }
+                _ => {
+                    let report = self.unknown_module(item, &import.path);
+                    self.errors.push(item.module(), report);
+                }
+            }
+            return;
+        };
+        // Names are still imported so the rest of the module can be checked
+        if let Some(cycle) = self.import_cycle(item.module(), imported) {
+            let cycle = cycle
+                .into_iter()
+                .map(|module_id| format!("`{}`", self.modules.id_to_path(module_id)))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            let report = self
+                .error(Code::ImportCycle, item)
+                .with_message("Import cycle")
+                .with_label(self.label(item, "here"))
+                .with_note(format!("the modules import each other: {cycle}"))
+                .finish();
+            self.errors.push(item.module(), report);
+        }
+        match &import.kind {
+            ImportKind::All => self.import_module(imported),
+            ImportKind::Alias(alias) => {
+                self.aliases.insert(alias.clone(), imported);
+            }
+            ImportKind::Names(names) => {
+                for name in names {
+                    self.import_name(item, imported, &name.name, name.alias.as_ref());
+                }
+            }
+        }
+    }
+
+    fn import_name(
+        &mut self,
+        item: GlobalIdx,
+        module_id: ModuleID,
+        name: &str,
+        alias: Option<&String>,
+    ) {
+        let identifier = Identifier::Name(name.to_string());
+        match self.modules.top_level_names(module_id).get(&identifier) {
+            Some(idx) => {
+                let definition = GlobalIdx::new(module_id, *idx);
+                if !self.modules.is_public(definition) {
+                    let report = self.private_item(item, definition);
+                    self.errors.push(item.module(), report);
+                }
+                self.scope.import(
+                    Identifier::Name(alias.map_or(name, String::as_str).to_string()),
+                    ResolvedDefinition(definition),
+                )
+            }
+            None => {
+                let report = self.not_in_module(item, module_id, name);
+                self.errors.push(item.module(), report);
+            }
+        }
+    }
+
+    /// `path` is what the user wrote, which can start with an alias.
+    fn unknown_module(&self, node: GlobalIdx, path: &str) -> Report {
+        let mut report = self
+            .error(Code::UnknownModule, node)
+            .with_message(format!("Couldn't find module `{path}`"))
+            .with_label(self.label(node, "here"));
+        let paths = self
+            .modules
+            .all_ids()
+            .filter(|module_id| *module_id != node.module())
+            .map(|module_id| self.modules.id_to_path(module_id))
+            .chain(self.aliases.keys().map(String::as_str));
+        if let Some(closest) = closest_match(path, paths) {
+            report = report.with_help(format!("did you mean `{closest}`?"));
+            if let Some(span) = self.path_span(node, path) {
+                report = report.with_fix(Fix::new(
+                    format!("Replace with `{closest}`"),
+                    vec![Edit::new((node.module(), span), closest)],
+                ));
+            }
+        }
+        report.finish()
+    }
+
+    fn not_in_module(&self, node: GlobalIdx, module_id: ModuleID, name: &str) -> Report {
+        let path = self.modules.id_to_path(module_id);
+        let mut report = self
+            .error(Code::UnknownName, node)
+            .with_message(format!("Couldn't find `{name}` in `{path}`"))
+            .with_label(self.label(node, "here"));
+        let names = self.modules.public_names(module_id);
+        let names = names.keys().filter_map(|identifier| match identifier {
+            Identifier::Name(name) => Some(name.as_str()),
+            _ => None,
+        });
+        if let Some(closest) = closest_match(name, names) {
+            report = report.with_help(format!("did you mean `{closest}`?"));
+        }
+        report.finish()
+    }
+
+    /// Where the module `path` is written in an import or qualified name.
+    fn path_span(&self, node: GlobalIdx, path: &str) -> Option<std::ops::Range<usize>> {
+        let span = self.span(node)?;
+        // Skips `use`, which could contain short paths
+        let start = match self.modules.get_node(node) {
+            Node::Import(_) => span.start + "use".len(),
+            _ => span.start,
+        };
+        let text = self.modules.get_ref(node.module()).source.text();
+        let start = start + text.get(start..span.end)?.find(path)?;
+        Some(start..start + path.len())
+    }
+
+    /// `module::name`, where `module` is a full module path, or starts with an
+    /// alias. These don't need the module to be imported.
+    fn resolve_qualified(&mut self, node: GlobalIdx, module: &str, name: &str) {
+        let (first, rest) = match module.split_once("::") {
+            Some((first, rest)) => (first, Some(rest)),
+            None => (module, None),
+        };
+        let path = match (self.aliases.get(first), rest) {
+            (Some(aliased), Some(rest)) => {
+                format!("{}::{rest}", self.modules.id_to_path(*aliased))
+            }
+            (Some(aliased), None) => self.modules.id_to_path(*aliased).to_string(),
+            (None, _) => module.to_string(),
+        };
+        let Some(module_id) = self.modules.get_id(&path) else {
+            let report = self.unknown_module(node, module);
+            self.errors.push(node.module(), report);
+            return;
+        };
+        let identifier = Identifier::Name(name.to_string());
+        match self.modules.top_level_names(module_id).get(&identifier) {
+            Some(idx) => {
+                let definition = GlobalIdx::new(module_id, *idx);
+                if !self.modules.is_public(definition) {
+                    let report = self.private_item(node, definition);
+                    self.errors.push(node.module(), report);
+                }
+                self.names.set(node, ResolvedDefinition(definition));
+            }
+            None => {
+                let report = self.not_in_module(node, module_id, name);
+                self.errors.push(node.module(), report);
+            }
+        }
+    }
+
+    /// The modules from `module_id` through `import` back to `module_id`, if
+    /// `import` leads back to it.
+    fn import_cycle(&self, module_id: ModuleID, import: ModuleID) -> Option<Vec<ModuleID>> {
+        let mut previous = HashMap::from([(import, import)]);
+        let mut queue = VecDeque::from([import]);
+        while let Some(current) = queue.pop_front() {
+            if current == module_id {
+                let mut cycle = vec![module_id];
+                let mut current = module_id;
+                while current != import {
+                    current = previous[&current];
+                    cycle.push(current);
+                }
+                cycle.push(module_id);
+                cycle.reverse();
+                return Some(cycle);
+            }
+            for next in self.modules.imports(current) {
+                if let Entry::Vacant(entry) = previous.entry(next) {
+                    entry.insert(current);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
     }
 
     fn import_module(&mut self, module_id: usize) {
         for (identifier, idx) in self.modules.get_ref(module_id).top_level_names() {
-            self.scope
-                .declare(identifier, ResolvedDefinition((module_id, idx).into()));
+            let definition = GlobalIdx::new(module_id, idx);
+            if self.modules.is_public(definition) {
+                self.scope
+                    .import(identifier, ResolvedDefinition(definition));
+            } else {
+                self.private.entry(identifier).or_insert(definition);
+            }
+        }
+    }
+
+    /// The first item declared with a name is the one it refers to, the later
+    /// ones are reported.
+    fn declare_items(&mut self, module_id: ModuleID) {
+        let mut declared = HashMap::<Identifier, GlobalIdx>::new();
+        for item in self.modules.top_level_ids(module_id) {
+            let Some(identifier) = self.modules.get_node(item).item_identifier() else {
+                continue;
+            };
+            if let Some(first) = declared.get(&identifier) {
+                let report = self
+                    .error(Code::DuplicateDefinition, item)
+                    .with_message(format!(
+                        "`{}` is defined multiple times",
+                        identifier_name(&identifier)
+                    ))
+                    .with_label(self.label(item, "redefined here"))
+                    .with_label(self.label(*first, "first defined here"))
+                    .with_help("rename one of them")
+                    .finish();
+                self.errors.push(module_id, report);
+                continue;
+            }
+            declared.insert(identifier.clone(), item);
+            self.scope.declare(identifier, ResolvedDefinition(item));
+        }
+    }
+
+    /// Returns whether `identifier` is declared. If several imports declare
+    /// it, it's reported and left unresolved. Private items of imported
+    /// modules are reported but still resolved, so their uses are checked.
+    fn resolve(&mut self, node: GlobalIdx, identifier: &Identifier) -> bool {
+        if let Identifier::Qualified { module, name } = identifier {
+            self.resolve_qualified(node, module, name);
+            return true;
+        }
+        let Some(resolved) = self.scope.resolve(identifier) else {
+            let Some(private) = self.private.get(identifier).copied() else {
+                return false;
+            };
+            let report = self.private_item(node, private);
+            self.errors.push(node.module(), report);
+            self.names.set(node, ResolvedDefinition(private));
+            return true;
+        };
+        match self.scope.ambiguous(identifier) {
+            Some(definitions) => {
+                let report = self.ambiguous(node, identifier, definitions);
+                self.errors.push(node.module(), report);
+            }
+            None => self.names.set(node, resolved),
         }
+        true
     }
 
     fn node(&mut self, node: impl Into<GlobalIdx>) {
         let node = node.into();
         let id = node.module();
         match self.modules.get_node(node) {
-            Node::System(system) => {
-                if let Some(ident) = &system.ident {
-                    self.scope
-                        .declare(Identifier::Name(ident.clone()), ResolvedDefinition(node));
-                }
-                self.node((id, system.block))
+            Node::System(system) => self.node((id, system.block)),
+            Node::Test(test) => {
+                self.in_test = true;
+                self.node((id, test.block));
+                self.in_test = false;
             }
             Node::Resource(typed_ident) | Node::Component(typed_ident) => {
                 self.node((id, typed_ident.r#type));
             }
             Node::TypeIdent(TypeIdent(identifier)) => {
-                if let Some(idx) = self.scope.resolve(&Identifier::Name(identifier.clone())) {
-                    self.names.set(node, idx);
-                } else {
+                let identifier = Identifier::Name(identifier.clone());
+                if !self.resolve(node, &identifier) {
                     self.errors.push(
                         node.module(),
-                        self.error(node)
-                            .with_message("Couldn't find name of type")
-                            .with_label(self.label(node, "here"))
-                            .finish(),
+                        self.not_found(
+                            Code::UnknownType,
+                            node,
+                            &identifier,
+                            "Couldn't find name of type",
+                        ),
                     );
                 }
             }
@@ -104,22 +371,22 @@ mod expr {
             match &expr {
                 Expr::Block(exprs) => {
                     self.scope.push_scope();
+                    let parent = self.block.replace(node);
                     for expr in exprs {
                         self.node((id, expr));
                     }
-                    self.scope.pop_scope();
+                    self.block = parent;
+                    let names = self.scope.pop_scope();
+                    self.scopes.set(node, Scope { parent, names });
                 }
                 Expr::Identifier(name) => {
-                    if let Some(resolved) = self.scope.resolve(name) {
-                        self.names.set(node, resolved);
-                    } else {
+                    if !self.resolve(node, name) {
                         self.errors.push(
                             node.module(),
-                            self.error(node)
-                                .with_message("Couldn't find name")
-                                .with_label(self.label(node, "here"))
-                                .finish(),
+                            self.not_found(Code::UnknownName, node, name, "Couldn't find name"),
                         );
+                    } else if !self.in_test {
+                        self.test_only(node, name);
                     }
                 }
                 Expr::FunctionCall { function, args } => {
@@ -134,7 +401,7 @@ mod expr {
                         .declare(Identifier::Name(entity.clone()), ResolvedDefinition(node));
                     self.node((id, block));
                 }
-                Expr::Number(_) | Expr::Poison => {}
+                Expr::Number(_) | Expr::String(_) | Expr::Poison => {}
                 Expr::ComponentAccess { entity, component } => {
                     self.node((id, entity));
                     self.node((id, component));
@@ -170,15 +437,15 @@ mod expr {
                     self.node((id, expr));
                 }
                 Expr::Struct { ident, fields } => {
-                    if let Some(resolved) = self.scope.resolve(ident) {
-                        self.names.set(node, resolved);
-                    } else {
+                    if !self.resolve(node, ident) {
                         self.errors.push(
                             node.module(),
-                            self.error(node)
-                                .with_message("Couldn't find name of a type")
-                                .with_label(self.label(node, "here"))
-                                .finish(),
+                            self.not_found(
+                                Code::UnknownType,
+                                node,
+                                ident,
+                                "Couldn't find name of a type",
+                            ),
                         );
                     }
 
@@ -192,9 +459,189 @@ mod expr {
 }
 
 impl ResolveCtx<'_> {
-    fn error(&self, node: GlobalIdx) -> ReportBuilder {
+    /// Suggests a similar visible name, or one from a module that isn't
+    /// imported, and importing the modules that declare `identifier`.
+    fn not_found(
+        &self,
+        code: Code,
+        node: GlobalIdx,
+        identifier: &Identifier,
+        message: &str,
+    ) -> Report {
+        let mut report = self
+            .error(code, node)
+            .with_message(message)
+            .with_label(self.label(node, "here"));
+        if let Identifier::Name(name) = identifier {
+            let visible = self.scope.ident_map();
+            let visible = visible.keys().filter_map(|identifier| match identifier {
+                Identifier::Name(name) => Some(name.as_str()),
+                _ => None,
+            });
+            if let Some(closest) = closest_match(name, visible) {
+                report = report.with_help(format!("did you mean `{closest}`?"));
+                if let Some(span) = self.name_span(node, name) {
+                    report = report.with_fix(Fix::new(
+                        format!("Replace with `{closest}`"),
+                        vec![Edit::new((node.module(), span), closest)],
+                    ));
+                }
+            } else if let Some((closest, path)) = self.closest_in_other_modules(node.module(), name)
+            {
+                report = report.with_help(format!("did you mean `{closest}` from `{path}`?"));
+            }
+        }
+        for fix in self.import_fixes(node.module(), identifier) {
+            report = report.with_fix(fix);
+        }
+        report.finish()
+    }
+
+    fn ambiguous(
+        &self,
+        node: GlobalIdx,
+        identifier: &Identifier,
+        definitions: &[ResolvedDefinition],
+    ) -> Report {
+        let paths = definitions
+            .iter()
+            .map(|definition| {
+                let module_id = definition.definition().module();
+                format!("`{}`", self.modules.id_to_path(module_id))
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut report = self
+            .error(Code::AmbiguousName, node)
+            .with_message(format!("`{}` is ambiguous", identifier_name(identifier)))
+            .with_label(self.label(node, "here"))
+            .with_note(format!("it's declared in {paths}"))
+            .with_help("declare it in this module to shadow them, or import only one");
+        // Builtins have no source to point at
+        for definition in definitions {
+            let definition = definition.definition();
+            if self
+                .modules
+                .get_ref(definition.module())
+                .spans
+                .get(definition.idx())
+                .is_some()
+            {
+                report = report.with_label(self.label(definition, "declared here"));
+            }
+        }
+        report.finish()
+    }
+
+    /// Systems run alongside each other, each with access to only what it
+    /// declares. Calling another system, or builtins like `spawn` that need
+    /// the whole world, is only sound in tests, which have a world to
+    /// themselves.
+    fn test_only(&mut self, node: GlobalIdx, identifier: &Identifier) {
+        let Some(definition) = self.names.get(node) else {
+            return;
+        };
+        let name = identifier_name(identifier);
+        let message = match self.modules.get_node(definition.definition()) {
+            Node::System(_) => format!("System `{name}` can only be called in tests"),
+            Node::Builtin { info, .. } if info.test_only => {
+                format!("`{name}` can only be used in tests")
+            }
+            _ => return,
+        };
+        let report = self
+            .error(Code::TestOnly, node)
+            .with_message(message)
+            .with_label(self.label(node, "here"))
+            .with_note("systems only have access to the components and resources they use")
+            .finish();
+        self.errors.push(node.module(), report);
+    }
+
+    /// `definition` is used from another module, but isn't `pub`.
+    fn private_item(&self, node: GlobalIdx, definition: GlobalIdx) -> Report {
+        let name = self
+            .modules
+            .get_node(definition)
+            .item_identifier()
+            .map(|identifier| identifier_name(&identifier))
+            .unwrap_or_default();
+        let path = self.modules.id_to_path(definition.module());
+        let mut report = self
+            .error(Code::PrivateItem, node)
+            .with_message(format!("`{name}` is private"))
+            .with_label(self.label(node, "here"))
+            .with_label(self.label(definition, "declared here"))
+            .with_note(format!("it can only be used in `{path}`"))
+            .with_help("declare it with `pub`");
+        if let Some(span) = self.span(definition) {
+            report = report.with_fix(Fix::new(
+                format!("Make `{name}` public"),
+                vec![Edit::new(
+                    (definition.module(), span.start..span.start),
+                    "pub ",
+                )],
+            ));
+        }
+        report.finish()
+    }
+
+    /// Exact matches are left to `import_fixes`.
+    fn closest_in_other_modules(&self, module_id: ModuleID, name: &str) -> Option<(String, &str)> {
+        let names = self
+            .modules
+            .all_ids()
+            .filter(|other| *other != module_id)
+            .flat_map(|other| {
+                self.modules
+                    .public_names(other)
+                    .into_keys()
+                    .filter_map(move |identifier| match identifier {
+                        Identifier::Name(name) => Some((name, other)),
+                        _ => None,
+                    })
+            })
+            .filter(|(other_name, _)| other_name != name)
+            .collect::<Vec<_>>();
+        let closest = closest_match(name, names.iter().map(|(name, _)| name.as_str()))?;
+        let (closest, other) = names.iter().find(|(name, _)| name == closest)?;
+        Some((closest.clone(), self.modules.id_to_path(*other)))
+    }
+
+    /// Where `name` is written in `node`, which for struct literals is their start.
+    fn name_span(&self, node: GlobalIdx, name: &str) -> Option<std::ops::Range<usize>> {
+        let start = self.span(node)?.start;
+        let text = self.modules.get_ref(node.module()).source.text();
+        text[start..]
+            .starts_with(name)
+            .then_some(start..start + name.len())
+    }
+
+    fn span(&self, node: GlobalIdx) -> Option<std::ops::Range<usize>> {
+        let ptr = self.modules.get_ref(node.module()).spans.get(node.idx())?;
+        Some(ptr.text_range().into())
+    }
+
+    fn import_fixes(&self, module_id: ModuleID, identifier: &Identifier) -> Vec<Fix> {
+        self.modules
+            .all_ids()
+            .filter(|other| {
+                *other != module_id && self.modules.public_names(*other).contains_key(identifier)
+            })
+            .map(|other| {
+                let path = self.modules.id_to_path(other);
+                Fix::new(
+                    format!("Import `{path}`"),
+                    vec![Edit::new((module_id, 0..0), format!("use {path}\n"))],
+                )
+            })
+            .collect()
+    }
+
+    fn error(&self, code: Code, node: GlobalIdx) -> ReportBuilder {
         Report::build(
             ReportKind::Error,
+            code,
             node.module(),
             self.modules
                 .get_ref(node.module())
@@ -221,3 +668,11 @@ impl ResolveCtx<'_> {
         .with_message(m)
     }
 }
+
+fn identifier_name(identifier: &Identifier) -> String {
+    match identifier {
+        Identifier::Name(name) => name.clone(),
+        Identifier::Operator(operator) => format!("{operator:?}"),
+        Identifier::Qualified { module, name } => format!("{module}::{name}"),
+    }
+}
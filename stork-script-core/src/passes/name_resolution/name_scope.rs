@@ -4,13 +4,17 @@ use crate::module_index::cache::ResolvedDefinition;
 
 use super::Identifier;
 
+/// Locals shadow the module's own items, which shadow imported ones. Imports
+/// don't shadow each other, a name declared by several of them is ambiguous.
 pub struct NameScope {
+    imports: HashMap<Identifier, Vec<ResolvedDefinition>>,
     scopes: Vec<HashMap<Identifier, ResolvedDefinition>>,
 }
 
 impl NameScope {
     pub fn new() -> Self {
         Self {
+            imports: Default::default(),
             scopes: vec![Default::default()],
         }
     }
@@ -19,28 +23,53 @@ impl NameScope {
         self.scopes.push(Default::default());
     }
 
-    pub fn pop_scope(&mut self) {
-        self.scopes.pop();
+    /// The names declared in the scope.
+    pub fn pop_scope(&mut self) -> HashMap<Identifier, ResolvedDefinition> {
+        self.scopes.pop().unwrap_or_default()
     }
 
     pub fn declare(&mut self, name: Identifier, node: impl Into<ResolvedDefinition>) {
         self.scopes.last_mut().unwrap().insert(name, node.into());
     }
 
+    pub fn import(&mut self, name: Identifier, node: impl Into<ResolvedDefinition>) {
+        let node = node.into();
+        let definitions = self.imports.entry(name).or_default();
+        if !definitions.contains(&node) {
+            definitions.push(node);
+        }
+    }
+
     pub fn resolve(&self, name: &Identifier) -> Option<ResolvedDefinition> {
         self.scopes
             .iter()
             .rev()
             .find_map(|scope| scope.get(name).copied())
+            .or_else(|| self.imports.get(name)?.first().copied())
     }
 
-    #[expect(dead_code)]
-    pub fn into_ident_map(self) -> HashMap<Identifier, ResolvedDefinition> {
-        self.scopes
-            .into_iter()
-            .fold(Default::default(), |mut a, b| {
-                a.extend(b);
-                a
-            })
+    /// Every import declaring `name` if there's more than one and nothing
+    /// shadows them.
+    pub fn ambiguous(&self, name: &Identifier) -> Option<&[ResolvedDefinition]> {
+        if self.scopes.iter().any(|scope| scope.contains_key(name)) {
+            return None;
+        }
+        self.imports
+            .get(name)
+            .filter(|definitions| definitions.len() > 1)
+            .map(Vec::as_slice)
+    }
+
+    /// Every visible name, inner scopes shadowing outer ones.
+    pub fn ident_map(&self) -> HashMap<Identifier, ResolvedDefinition> {
+        let imports = self
+            .imports
+            .iter()
+            .map(|(name, definitions)| (name.clone(), definitions[0]))
+            .collect();
+        self.scopes.iter().cloned().fold(imports, |mut a, b| {
+            a.extend(b);
+            a
+        })
     }
 }
@@ -1,5 +1,7 @@
 #[path = "borrow_resolution/borrow_resolution.rs"]
 pub mod borrow_resolution;
+#[path = "lint/lint.rs"]
+pub mod lint;
 pub mod lower;
 #[path = "name_resolution/name_resolution.rs"]
 pub mod name_resolution;
@@ -46,6 +46,13 @@ impl PrintCtx<'_> {
                     self.node((id, system.block), indent + 1),
                 )
             }
+            Node::Test(test) => {
+                format!(
+                    "Test {:?}\n{}",
+                    test.name,
+                    self.node((id, test.block), indent + 1),
+                )
+            }
             Node::Component(typed_ident) => {
                 format!(
                     "Component {} {}",
@@ -124,6 +131,7 @@ impl PrintCtx<'_> {
                         .map_or(Default::default(), |n| format!(" = {n:?}")),
                 ),
                 Expr::Number(number) => format!("Number {number:?}{te}"),
+                Expr::String(string) => format!("String {string:?}{te}"),
                 Expr::ComponentAccess { entity, component } => format!(
                     "ComponentAccess{te}\n{}\n{}",
                     self.node((id, entity), indent + 1),
@@ -1,6 +1,7 @@
 use std::fmt::{Debug, Display};
 
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Default)]
 pub struct ResolvedType {
@@ -37,7 +38,17 @@ impl From<InnerResolvedType> for Option<ResolvedType> {
     }
 }
 
-#[derive(Clone, Default, PartialEq, Eq)]
+impl InnerResolvedType {
+    /// Systems can be called like functions without arguments
+    pub fn system() -> Self {
+        InnerResolvedType::Function {
+            params: Vec::new(),
+            ret: Box::new(InnerResolvedType::Unit),
+        }
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum InnerResolvedType {
     Struct {
         fields: Vec<(String, InnerResolvedType)>,
@@ -50,6 +61,7 @@ pub enum InnerResolvedType {
     Unit,
     F32,
     Bool,
+    String,
     Recursion,
     #[default]
     Poison,
@@ -68,25 +80,45 @@ impl Debug for InnerResolvedType {
             InnerResolvedType::Unit => f.write_str("InnerResolvedType::Unit"),
             InnerResolvedType::F32 => f.write_str("InnerResolvedType::F32"),
             InnerResolvedType::Bool => f.write_str("InnerResolvedType::Bool"),
+            InnerResolvedType::String => f.write_str("InnerResolvedType::String"),
             InnerResolvedType::Recursion => f.write_str("InnerResolvedType::Recursion"),
         }
     }
 }
 
+impl InnerResolvedType {
+    /// Like [`Display`] but without the surrounding quotes, for nesting and tooling.
+    pub fn plain(&self) -> String {
+        match self {
+            InnerResolvedType::Struct { fields } if fields.is_empty() => "{}".to_string(),
+            InnerResolvedType::Struct { fields } => format!(
+                "{{ {} }}",
+                fields
+                    .iter()
+                    .map(|(name, r#type)| format!("{name}: {}", r#type.plain()))
+                    .format(", ")
+            ),
+            InnerResolvedType::Function { params, ret } => format!(
+                "fn({}) -> {}",
+                params.iter().map(|p| p.plain()).format(", "),
+                ret.plain()
+            ),
+            InnerResolvedType::Entity => "Entity".to_string(),
+            InnerResolvedType::Poison => "Unknown".to_string(),
+            InnerResolvedType::Unit => "()".to_string(),
+            InnerResolvedType::F32 => "f32".to_string(),
+            InnerResolvedType::Bool => "bool".to_string(),
+            InnerResolvedType::String => "String".to_string(),
+            InnerResolvedType::Recursion => "INTERNAL".to_string(),
+        }
+    }
+}
+
 impl Display for InnerResolvedType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            InnerResolvedType::Struct { .. } => f.write_str("'struct {TODO}'"),
-            InnerResolvedType::Function { params, ret } => f.write_fmt(format_args!(
-                "'fn({}) -> {ret}'",
-                params.iter().map(|p| p.to_string()).format(", "),
-            )),
-            InnerResolvedType::Entity => f.write_str("'Entity'"),
-            InnerResolvedType::Poison => f.write_str("'Unknown'"),
-            InnerResolvedType::Unit => f.write_str("'()'"),
-            InnerResolvedType::F32 => f.write_str("'f32'"),
-            InnerResolvedType::Bool => f.write_str("'bool'"),
             InnerResolvedType::Recursion => f.write_str("INTERNAL"),
+            _ => write!(f, "'{}'", self.plain()),
         }
     }
 }
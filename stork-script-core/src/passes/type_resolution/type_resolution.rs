@@ -1,12 +1,13 @@
 mod resolved_type;
 
 use crate::{
+    diagnostic::Code,
     hir::*,
     module_index::{
         cache::{Cache, ErrorMap, NameMap, TypeMap},
         ModuleCollection,
     },
-    report::{Label, Report, ReportBuilder, ReportKind},
+    report::{closest_match, Edit, Fix, Label, Report, ReportBuilder, ReportKind},
 };
 use itertools::{EitherOrBoth, Itertools};
 pub use resolved_type::{InnerResolvedType, ResolvedType};
@@ -33,12 +34,16 @@ struct ResolveCtx<'c> {
 impl ResolveCtx<'_> {
     fn resolve(&mut self, idx: impl Into<GlobalIdx>) -> Option<ResolvedType> {
         let idx = idx.into();
+        // Known without walking the body, which is done when the system itself is visited
+        if let Node::System(_) = self.modules.get_node(idx) {
+            return InnerResolvedType::system().into();
+        }
         if let Some(r#type) = self.types.get(idx) {
             if r#type.inner == InnerResolvedType::Recursion {
                 self.types.set(idx, InnerResolvedType::Poison);
                 self.errors.push(
                     idx.module(),
-                    self.error(idx)
+                    self.error(Code::RecursiveType, idx)
                         .with_message("Found recursive type")
                         .with_label(self.label(idx, "here"))
                         .finish(),
@@ -79,6 +84,10 @@ impl ResolveCtx<'_> {
             match self.modules.get_node(node) {
                 Node::System(system) => {
                     self.node((id, system.block));
+                    InnerResolvedType::system()
+                }
+                Node::Test(test) => {
+                    self.node((id, test.block));
                     InnerResolvedType::Poison
                 }
                 Node::Resource(typed_ident) | Node::Component(typed_ident) => {
@@ -118,6 +127,7 @@ impl ResolveCtx<'_> {
                 }
                 Expr::Identifier(_) => return self.resolve(self.names.get(node)?.definition()),
                 Expr::Number(_) => InnerResolvedType::F32,
+                Expr::String(_) => InnerResolvedType::String,
                 Expr::FunctionCall { function, args } => {
                     let function = (id, *function);
                     let is_not_op = matches!(
@@ -130,7 +140,7 @@ impl ResolveCtx<'_> {
                         r#type => {
                             self.errors.push(
                                 node.module(),
-                                self.error(node)
+                                self.error(Code::NotAFunction, node)
                                     .with_message("Call expression requires function")
                                     .with_label(self.label(function, "here"))
                                     .with_note(format!("instead it has type {}", r#type))
@@ -159,7 +169,7 @@ impl ResolveCtx<'_> {
                                 if !already_showed_error {
                                     self.errors.push(
                                         node.module(),
-                                        self.error(node)
+                                        self.error(Code::TooManyArguments, node)
                                             .with_message("Too many arguments")
                                             .with_label(self.label(node, "in this function call"))
                                             .with_note(format!(
@@ -172,17 +182,20 @@ impl ResolveCtx<'_> {
                                 already_showed_error = true;
                             }
                             EitherOrBoth::Right(_) => {
-                                self.errors.push(
-                                    node.module(),
-                                    self.error(node)
-                                        .with_message("Too few arguments")
-                                        .with_label(self.label(node, "in this function call"))
-                                        .with_note(format!(
-                                            "function takes {} arguments",
-                                            params.len()
-                                        ))
-                                        .finish(),
-                                );
+                                let mut report = self
+                                    .error(Code::TooFewArguments, node)
+                                    .with_message("Too few arguments")
+                                    .with_label(self.label(node, "in this function call"))
+                                    .with_note(format!(
+                                        "function takes {} arguments",
+                                        params.len()
+                                    ));
+                                if let Some(fix) =
+                                    self.missing_arguments_fix(node, args, &params[args.len()..])
+                                {
+                                    report = report.with_fix(fix);
+                                }
+                                self.errors.push(node.module(), report.finish());
                                 break;
                             }
                         }
@@ -213,13 +226,15 @@ impl ResolveCtx<'_> {
                     return Some(resource_type);
                 }
                 Expr::MemberAccess { base, member } => {
+                    // Resolved first so incomplete accesses like `a.` still have a typed base
+                    let base = self.node((id, base));
                     let member_idx = (id, member);
                     let Some(Identifier::Name(member)) =
                         self.modules.get_node(member_idx).as_expr_identifier()
                     else {
                         self.errors.push(
                             node.module(),
-                            self.error(node)
+                            self.error(Code::FieldNameNotLiteral, node)
                                 .with_message(
                                     "You can get a field only with a literal string names",
                                 )
@@ -229,13 +244,12 @@ impl ResolveCtx<'_> {
                         return Some(InnerResolvedType::Poison.into());
                     };
 
-                    let base = self.node((id, base))?;
-                    let fields = match base.inner {
+                    let fields = match base?.inner {
                         InnerResolvedType::Struct { fields } => fields,
                         r#type => {
                             self.errors.push(
                                 node.module(),
-                                self.error(node)
+                                self.error(Code::NotAStruct, node)
                                     .with_message("Only structs contain fields")
                                     .with_label(self.label(member_idx, "here"))
                                     .with_note(format!("instead it has type {}", r#type))
@@ -245,12 +259,14 @@ impl ResolveCtx<'_> {
                         }
                     };
 
-                    fields
-                        .iter()
-                        .find(|(name, _)| name == member)
-                        .cloned()
-                        .map(|(_, r#type)| r#type)
-                        .unwrap_or_default()
+                    match fields.iter().find(|(name, _)| name == member) {
+                        Some((_, r#type)) => r#type.clone(),
+                        None => {
+                            let span = self.span(member_idx.into());
+                            self.unknown_field(node, member, span, &fields);
+                            InnerResolvedType::Poison
+                        }
+                    }
                 }
                 Expr::Assign { lvalue, expr } => {
                     let lvalue_type = self.node((id, lvalue))?;
@@ -307,7 +323,16 @@ impl ResolveCtx<'_> {
                     for (_, field) in fields {
                         self.node((id, field));
                     }
-                    return self.resolve(self.names.get(node)?.definition());
+                    let r#type = self.resolve(self.names.get(node)?.definition())?;
+                    if let InnerResolvedType::Struct { fields: known } = &r#type.inner {
+                        for (name, value) in fields {
+                            if !known.iter().any(|(known, _)| known == name) {
+                                let span = self.literal_field_span(node, name, (id, *value).into());
+                                self.unknown_field(node, name, span, known);
+                            }
+                        }
+                    }
+                    return Some(r#type);
                 }
             }
             .into(),
@@ -321,7 +346,7 @@ impl ResolveCtx<'_> {
         if r#type.inner != InnerResolvedType::Bool && !r#type.component_or_resource {
             self.errors.push(
                 node.module(),
-                self.error(node)
+                self.error(Code::NotACondition, node)
                     .with_message(format!(
                         "Expected {} or ECS access",
                         InnerResolvedType::Bool
@@ -338,7 +363,7 @@ impl ResolveCtx<'_> {
         if a.inner != *b {
             self.errors.push(
                 node.module(),
-                self.error(node)
+                self.error(Code::MismatchedTypes, node)
                     .with_message(format!("Expected {}", b))
                     .with_label(self.label(node, "here"))
                     .with_note(format!("found {}", a.inner))
@@ -352,7 +377,7 @@ impl ResolveCtx<'_> {
         if !r#type.component_or_resource {
             self.errors.push(
                 node.module(),
-                self.error(node)
+                self.error(Code::NotFromEcs, node)
                     .with_message("Type should be a component".to_string())
                     .with_label(self.label(node, "here"))
                     .finish(),
@@ -360,10 +385,89 @@ impl ResolveCtx<'_> {
         }
     }
 
-    fn error(&self, node: impl Into<GlobalIdx>) -> ReportBuilder {
+    /// Placeholders for the `missing` parameters, inserted before the `)`.
+    fn missing_arguments_fix(
+        &self,
+        node: GlobalIdx,
+        args: &[Idx],
+        missing: &[InnerResolvedType],
+    ) -> Option<Fix> {
+        let span = self.span(node)?;
+        let text = self.modules.get_ref(node.module()).source.text();
+        if !text[span.clone()].ends_with(')') {
+            return None;
+        }
+        let placeholders = missing
+            .iter()
+            .map(|r#type| match r#type {
+                InnerResolvedType::F32 => Some("0"),
+                InnerResolvedType::Bool => Some("0 == 0"),
+                InnerResolvedType::String => Some("\"\""),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let separator = if args.is_empty() { "" } else { ", " };
+        Some(Fix::new(
+            "Add the missing arguments",
+            vec![Edit::new(
+                (node.module(), span.end - 1..span.end - 1),
+                format!("{separator}{}", placeholders.join(", ")),
+            )],
+        ))
+    }
+
+    /// Field names of struct literals aren't nodes, but they're right before
+    /// their values.
+    fn literal_field_span(
+        &self,
+        node: GlobalIdx,
+        name: &str,
+        value: GlobalIdx,
+    ) -> Option<std::ops::Range<usize>> {
+        let (r#struct, value) = self.span(node).zip(self.span(value))?;
+        let text = self.modules.get_ref(node.module()).source.text();
+        let start = r#struct.start + text[r#struct.start..value.start].rfind(name)?;
+        Some(start..start + name.len())
+    }
+
+    /// Reports a field that a struct doesn't have, either in a literal or an
+    /// access, and suggests the closest one that it does.
+    fn unknown_field(
+        &mut self,
+        node: GlobalIdx,
+        name: &str,
+        name_span: Option<std::ops::Range<usize>>,
+        known: &[(String, InnerResolvedType)],
+    ) {
+        let mut report = self
+            .error(Code::UnknownField, node)
+            .with_message(format!("Unknown field `{name}`"))
+            .with_label(match &name_span {
+                Some(span) => Label::new((node.module(), span.clone())).with_message("here"),
+                None => self.label(node, "here"),
+            });
+        if let Some(closest) = closest_match(name, known.iter().map(|(known, _)| known.as_str())) {
+            report = report.with_help(format!("did you mean `{closest}`?"));
+            if let Some(span) = name_span {
+                report = report.with_fix(Fix::new(
+                    format!("Replace with `{closest}`"),
+                    vec![Edit::new((node.module(), span), closest)],
+                ));
+            }
+        }
+        self.errors.push(node.module(), report.finish());
+    }
+
+    fn span(&self, node: GlobalIdx) -> Option<std::ops::Range<usize>> {
+        let ptr = self.modules.get_ref(node.module()).spans.get(node.idx())?;
+        Some(ptr.text_range().into())
+    }
+
+    fn error(&self, code: Code, node: impl Into<GlobalIdx>) -> ReportBuilder {
         let node = node.into();
         Report::build(
             ReportKind::Error,
+            code,
             node.module(),
             self.modules
                 .get_ref(node.module())
@@ -1,12 +1,217 @@
 use ariadne::Color;
-use std::ops::Range;
+use std::{io, ops::Range};
 
-use crate::module_index::ModuleID;
+use crate::{diagnostic::Code, module_index::ModuleID};
 
 pub type Span = (ModuleID, Range<usize>);
-pub type Label = ariadne::Label<Span>;
-pub type ReportBuilder = ariadne::ReportBuilder<'static, Span>;
-pub type Report = ariadne::Report<'static, Span>;
 pub type Result<T, E = Box<Report>> = std::result::Result<T, E>;
 pub type ReportKind = ariadne::ReportKind<'static>;
 pub const INTERNAL_REPORT_KIND: ReportKind = ReportKind::Custom("internal", Color::BrightMagenta);
+
+/// A diagnostic, mirrors [`ariadne::Report`] but keeps its parts readable for
+/// tools like the language server.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub kind: ReportKind,
+    pub code: Code,
+    /// Module and byte offset the report is about
+    pub location: (ModuleID, usize),
+    pub message: Option<String>,
+    pub labels: Vec<Label>,
+    pub note: Option<String>,
+    pub help: Option<String>,
+    /// Edits that would fix it, ariadne doesn't show these
+    pub fixes: Vec<Fix>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Label {
+    /// Byte range
+    pub span: Span,
+    pub message: Option<String>,
+}
+
+/// A suggested fix, like adding a missing import.
+#[derive(Debug, Clone)]
+pub struct Fix {
+    pub title: String,
+    pub edits: Vec<Edit>,
+}
+
+/// Replaces the text in `span` with `text`, an empty span inserts.
+#[derive(Debug, Clone)]
+pub struct Edit {
+    pub span: Span,
+    pub text: String,
+}
+
+pub struct ReportBuilder(Report);
+
+impl Report {
+    pub fn build(
+        kind: ReportKind,
+        code: Code,
+        module_id: ModuleID,
+        offset: usize,
+    ) -> ReportBuilder {
+        ReportBuilder(Report {
+            kind,
+            code,
+            location: (module_id, offset),
+            message: None,
+            labels: Vec::new(),
+            note: None,
+            help: None,
+            fixes: Vec::new(),
+        })
+    }
+
+    /// Errors and internal errors, as opposed to warnings and advice.
+    pub fn is_error(&self) -> bool {
+        matches!(self.kind, ReportKind::Error | ReportKind::Custom(..))
+    }
+
+    /// Where the report should be shown, the first label or else its location.
+    pub fn span(&self) -> Span {
+        self.labels.first().map_or_else(
+            || (self.location.0, self.location.1..self.location.1),
+            |label| label.span.clone(),
+        )
+    }
+
+    pub fn print<C: ariadne::Cache<ModuleID>>(&self, cache: C) -> io::Result<()> {
+        self.to_ariadne().print(cache)
+    }
+
+    pub fn eprint<C: ariadne::Cache<ModuleID>>(&self, cache: C) -> io::Result<()> {
+        self.to_ariadne().eprint(cache)
+    }
+
+    fn to_ariadne(&self) -> ariadne::Report<'static, Span> {
+        let mut report = ariadne::Report::build(self.kind, self.location.0, self.location.1)
+            .with_code(self.code.as_str());
+        if let Some(message) = &self.message {
+            report.set_message(message);
+        }
+        for label in &self.labels {
+            let mut ariadne_label = ariadne::Label::new(label.span.clone());
+            if let Some(message) = &label.message {
+                ariadne_label = ariadne_label.with_message(message);
+            }
+            report.add_label(ariadne_label);
+        }
+        if let Some(note) = &self.note {
+            report.set_note(note);
+        }
+        if let Some(help) = &self.help {
+            report.set_help(help);
+        }
+        report.finish()
+    }
+}
+
+impl ReportBuilder {
+    pub fn with_message<M: ToString>(mut self, message: M) -> Self {
+        self.0.message = Some(message.to_string());
+        self
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.0.labels.push(label);
+        self
+    }
+
+    pub fn with_note<N: ToString>(mut self, note: N) -> Self {
+        self.0.note = Some(note.to_string());
+        self
+    }
+
+    pub fn with_help<N: ToString>(mut self, help: N) -> Self {
+        self.0.help = Some(help.to_string());
+        self
+    }
+
+    pub fn with_fix(mut self, fix: Fix) -> Self {
+        self.0.fixes.push(fix);
+        self
+    }
+
+    pub fn finish(self) -> Report {
+        self.0
+    }
+}
+
+impl Fix {
+    pub fn new<T: ToString>(title: T, edits: Vec<Edit>) -> Self {
+        Self {
+            title: title.to_string(),
+            edits,
+        }
+    }
+}
+
+impl Edit {
+    pub fn new<T: ToString>(span: Span, text: T) -> Self {
+        Self {
+            span,
+            text: text.to_string(),
+        }
+    }
+}
+
+impl Label {
+    pub fn new(span: Span) -> Self {
+        Self {
+            span,
+            message: None,
+        }
+    }
+
+    pub fn with_message<M: ToString>(mut self, message: M) -> Self {
+        self.message = Some(message.to_string());
+        self
+    }
+}
+
+/// The candidate most like `name`, ignoring case first and then by edit
+/// distance, if any is close enough to be a likely typo. Names shorter than
+/// three characters only match by case.
+pub fn closest_match<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let name = name.to_lowercase();
+    let max_distance = name.chars().count() / 3;
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(&name, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance where swapping two adjacent chars is one edit too.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = distances[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = substitution
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
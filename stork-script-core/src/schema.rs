@@ -0,0 +1,104 @@
+//! A serializable description of the `std` module, so tools without the game's
+//! `TypeRegistry` can still resolve and type check scripts against it.
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hir::{BuiltinInfo, Identifier, Node},
+    module_index::Module,
+    passes::type_resolution::{InnerResolvedType, ResolvedType},
+};
+
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schema {
+    pub version: u32,
+    pub builtins: Vec<Builtin>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Builtin {
+    pub identifier: Identifier,
+    pub r#type: InnerResolvedType,
+    #[serde(flatten)]
+    pub info: BuiltinInfo,
+}
+
+impl Schema {
+    /// Describes the builtins of a module, like the `std` of a running game.
+    pub fn from_module(module: &Module) -> Self {
+        let builtins = module
+            .top_level_ids()
+            .filter_map(|idx| match &module.nodes[idx] {
+                Node::Builtin {
+                    identifier,
+                    r#type,
+                    info,
+                    ..
+                } => Some(Builtin {
+                    identifier: identifier.clone(),
+                    r#type: r#type.inner.clone(),
+                    info: info.clone(),
+                }),
+                _ => None,
+            })
+            .collect();
+        Self {
+            version: SCHEMA_VERSION,
+            builtins,
+        }
+    }
+
+    /// A module with the described builtins. They carry no data, so it can be
+    /// compiled against but not run.
+    pub fn to_module(&self) -> Module {
+        let mut module = Module {
+            parser_errors: Default::default(),
+            nodes: Default::default(),
+            spans: Default::default(),
+            source: String::default().into(),
+            top_level: Default::default(),
+            public: Default::default(),
+        };
+        for builtin in &self.builtins {
+            module.alloc_top_level(Node::Builtin {
+                identifier: builtin.identifier.clone(),
+                r#type: ResolvedType {
+                    inner: builtin.r#type.clone(),
+                    component_or_resource: builtin.info.component || builtin.info.resource,
+                },
+                effects: Default::default(),
+                info: builtin.info.clone(),
+                data: Box::new(()),
+            });
+        }
+        module
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read schema {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Couldn't load schema {}", path.display()))
+    }
+
+    /// A schema from the JSON `save` writes.
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let schema: Self = serde_json::from_str(text)?;
+        if schema.version != SCHEMA_VERSION {
+            bail!(
+                "The schema has version {}, expected {SCHEMA_VERSION}",
+                schema.version
+            );
+        }
+        Ok(schema)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let text = serde_json::to_string_pretty(self)?;
+        fs::write(path, text).with_context(|| format!("Couldn't write schema {}", path.display()))
+    }
+}
@@ -0,0 +1,81 @@
+//! Debug Adapter Protocol server for scripts running inside of a bevy [`World`].
+//!
+//! Attaching installs a [`Debugger`] into the world's [`VMModuleIndex`] and serves
+//! a single client at a time on a background thread. While a script is stopped
+//! at a breakpoint the system running it, and therefore the whole schedule, is blocked.
+
+mod protocol;
+mod server;
+
+use std::{
+    collections::BTreeSet,
+    io::{self, BufReader},
+    net::{SocketAddr, TcpListener, ToSocketAddrs},
+    thread,
+};
+
+use bevy_ecs::world::World;
+use server::{ModuleInfo, Server};
+use stork_script_bevy::{debugger::Debugger, vm_module_index::VMModuleIndex};
+use stork_script_core::hir::{Expr, Node};
+
+/// Listens for DAP clients on `addr` and returns the address that was bound.
+///
+/// Should be called after [`VMModuleIndex::compile`].
+pub fn attach_tcp(world: &mut World, addr: impl ToSocketAddrs) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(addr)?;
+    let local_addr = listener.local_addr()?;
+    let server = install(world);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let Ok(reader) = stream.try_clone() else {
+                continue;
+            };
+            server.run_session(BufReader::new(reader), stream);
+        }
+    });
+    Ok(local_addr)
+}
+
+/// Serves a single DAP client over stdin and stdout.
+///
+/// Scripts shouldn't `print` while attached like this, as it would corrupt the protocol.
+pub fn attach_stdio(world: &mut World) {
+    let server = install(world);
+    thread::spawn(move || server.run_session(BufReader::new(io::stdin()), io::stdout()));
+}
+
+fn install(world: &mut World) -> Server {
+    let mut vm = world.resource_mut::<VMModuleIndex>();
+    let (debugger, handle) = Debugger::new();
+    vm.debugger = Some(debugger);
+
+    let modules = vm
+        .index
+        .modules
+        .all_ids()
+        .map(|module_id| {
+            let module = vm.index.modules.get_ref(module_id);
+            let statement_lines = module
+                .nodes
+                .iter()
+                .filter_map(|(_, node)| match node {
+                    Node::Expr(Expr::Block(exprs)) => Some(exprs),
+                    _ => None,
+                })
+                .flatten()
+                .filter_map(|expr| Some(module.line_col(*expr)?.0))
+                .collect::<BTreeSet<_>>();
+            ModuleInfo {
+                path: vm.index.modules.id_to_path(module_id).to_string(),
+                source: module.source.text().to_string(),
+                statement_lines,
+            }
+        })
+        .collect();
+
+    Server::new(handle, modules)
+}
@@ -0,0 +1,352 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::{BufRead, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use serde_json::{json, Value};
+use stork_script_bevy::debugger::{
+    DebugCommand, DebugEvent, DebuggerHandle, StackFrame, StopReason, Variable,
+};
+use stork_script_core::module_index::ModuleID;
+
+use crate::protocol::{read_message, write_message};
+
+/// Scripts run on a single thread as far as the client is concerned.
+const THREAD_ID: u64 = 1;
+
+pub struct ModuleInfo {
+    pub path: String,
+    pub source: String,
+    /// Zero-indexed lines on which some statement starts
+    pub statement_lines: BTreeSet<usize>,
+}
+
+#[derive(Clone)]
+pub struct Server {
+    handle: Arc<DebuggerHandle>,
+    /// Indexed by [`ModuleID`]
+    modules: Arc<Vec<ModuleInfo>>,
+    session: Arc<Mutex<Option<Session>>>,
+}
+
+struct Session {
+    writer: Box<dyn Write + Send>,
+    seq: u64,
+    lines_start_at_1: bool,
+    columns_start_at_1: bool,
+    /// Paths that the client used for modules in `setBreakpoints`
+    client_paths: HashMap<ModuleID, String>,
+    /// Stack of the currently stopped system
+    frames: Vec<StackFrame>,
+    /// Indexed by `variablesReference - 1`, cleared on resume
+    variables: Vec<Vec<Variable>>,
+}
+
+impl Server {
+    pub fn new(handle: DebuggerHandle, modules: Vec<ModuleInfo>) -> Self {
+        let server = Self {
+            handle: Arc::new(handle),
+            modules: Arc::new(modules),
+            session: Default::default(),
+        };
+        let pump = server.clone();
+        thread::spawn(move || pump.pump_events());
+        server
+    }
+
+    /// Serves a single client until it disconnects.
+    pub fn run_session(&self, mut reader: impl BufRead, writer: impl Write + Send + 'static) {
+        *self.session.lock().unwrap() = Some(Session {
+            writer: Box::new(writer),
+            seq: 1,
+            lines_start_at_1: true,
+            columns_start_at_1: true,
+            client_paths: HashMap::new(),
+            frames: Vec::new(),
+            variables: Vec::new(),
+        });
+
+        while let Ok(Some(message)) = read_message(&mut reader) {
+            if message["type"] != "request" {
+                continue;
+            }
+            self.request(&message);
+            if message["command"] == "disconnect" {
+                break;
+            }
+        }
+
+        self.handle.clear_breakpoints();
+        *self.session.lock().unwrap() = None;
+        self.handle.resume(DebugCommand::Continue);
+    }
+
+    fn pump_events(&self) {
+        while let Some(event) = self.handle.next_event() {
+            match event {
+                DebugEvent::Stopped { reason, frames } => {
+                    let mut session = self.session.lock().unwrap();
+                    let Some(session) = session.as_mut() else {
+                        self.handle.resume(DebugCommand::Continue);
+                        continue;
+                    };
+                    session.frames = frames;
+                    session.variables.clear();
+                    let reason = match reason {
+                        StopReason::Breakpoint => "breakpoint",
+                        StopReason::Step => "step",
+                        StopReason::Pause => "pause",
+                    };
+                    session.event(
+                        "stopped",
+                        json!({
+                            "reason": reason,
+                            "threadId": THREAD_ID,
+                            "allThreadsStopped": true,
+                        }),
+                    );
+                }
+            }
+        }
+    }
+
+    fn request(&self, request: &Value) {
+        let mut session = self.session.lock().unwrap();
+        let Some(session) = session.as_mut() else {
+            return;
+        };
+        let arguments = &request["arguments"];
+        let command = request["command"].as_str().unwrap_or_default();
+
+        let resume = |session: &mut Session, command| {
+            session.frames.clear();
+            session.variables.clear();
+            self.handle.resume(command);
+            Ok(json!({ "allThreadsContinued": true }))
+        };
+
+        let body = match command {
+            "initialize" => {
+                session.lines_start_at_1 = arguments["linesStartAt1"].as_bool().unwrap_or(true);
+                session.columns_start_at_1 = arguments["columnsStartAt1"].as_bool().unwrap_or(true);
+                Ok(json!({
+                    "supportsConfigurationDoneRequest": true,
+                }))
+            }
+            "launch" | "attach" | "configurationDone" | "disconnect" => Ok(Value::Null),
+            "setBreakpoints" => Ok(self.set_breakpoints(session, arguments)),
+            "threads" => Ok(json!({
+                "threads": [{ "id": THREAD_ID, "name": "scripts" }],
+            })),
+            "stackTrace" => Ok(self.stack_trace(session)),
+            "scopes" => self.scopes(session, arguments),
+            "variables" => self.variables(session, arguments),
+            "source" => self.source(arguments),
+            "continue" => resume(session, DebugCommand::Continue),
+            "next" => resume(session, DebugCommand::StepOver),
+            "stepIn" => resume(session, DebugCommand::StepIn),
+            "stepOut" => resume(session, DebugCommand::StepOut),
+            "pause" => {
+                self.handle.pause();
+                Ok(Value::Null)
+            }
+            _ => Err(format!("Unsupported request {command}")),
+        };
+
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": command,
+            "success": body.is_ok(),
+        });
+        match body {
+            Ok(Value::Null) => {}
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = message.into(),
+        }
+        session.send(response);
+
+        if command == "initialize" {
+            session.event("initialized", Value::Null);
+        }
+    }
+
+    fn set_breakpoints(&self, session: &mut Session, arguments: &Value) -> Value {
+        let source = &arguments["source"];
+        let requested: Vec<u64> = match arguments["breakpoints"].as_array() {
+            Some(breakpoints) => breakpoints
+                .iter()
+                .filter_map(|b| b["line"].as_u64())
+                .collect(),
+            None => arguments["lines"]
+                .as_array()
+                .map(|lines| lines.iter().filter_map(Value::as_u64).collect())
+                .unwrap_or_default(),
+        };
+
+        let Some(module) = self.module_for_source(source) else {
+            let breakpoints = requested
+                .iter()
+                .map(|line| {
+                    json!({
+                        "verified": false,
+                        "line": line,
+                        "message": "This file isn't a loaded script module",
+                    })
+                })
+                .collect::<Vec<_>>();
+            return json!({ "breakpoints": breakpoints });
+        };
+        if let Some(path) = source["path"].as_str() {
+            session.client_paths.insert(module, path.to_string());
+        }
+
+        let line_offset = session.line_offset();
+        let mut lines = Vec::new();
+        let breakpoints = requested
+            .iter()
+            .map(|&line| {
+                let line = (line as usize).saturating_sub(line_offset);
+                // Move the breakpoint to the next line that can actually be stopped at
+                match self.modules[module].statement_lines.range(line..).next() {
+                    Some(&line) => {
+                        lines.push(line);
+                        json!({ "verified": true, "line": line + line_offset })
+                    }
+                    None => json!({
+                        "verified": false,
+                        "line": line + line_offset,
+                        "message": "There is no statement on or after this line",
+                    }),
+                }
+            })
+            .collect::<Vec<_>>();
+        self.handle.set_breakpoints(module, lines);
+
+        json!({ "breakpoints": breakpoints })
+    }
+
+    fn stack_trace(&self, session: &Session) -> Value {
+        let frames = session
+            .frames
+            .iter()
+            .enumerate()
+            .map(|(i, frame)| {
+                json!({
+                    "id": i + 1,
+                    "name": frame.name,
+                    "line": frame.line + session.line_offset(),
+                    "column": frame.column + session.column_offset(),
+                    "source": self.source_json(session, frame.module),
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({
+            "stackFrames": frames,
+            "totalFrames": session.frames.len(),
+        })
+    }
+
+    fn scopes(&self, session: &mut Session, arguments: &Value) -> Result<Value, String> {
+        let frame = arguments["frameId"]
+            .as_u64()
+            .and_then(|id| session.frames.get((id as usize).checked_sub(1)?))
+            .ok_or("Unknown frame")?;
+        let scopes = frame.scopes.clone();
+        let scopes = scopes
+            .into_iter()
+            .map(|scope| {
+                json!({
+                    "name": scope.name,
+                    "variablesReference": session.alloc_variables(scope.variables),
+                    "expensive": false,
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(json!({ "scopes": scopes }))
+    }
+
+    fn variables(&self, session: &mut Session, arguments: &Value) -> Result<Value, String> {
+        let variables = arguments["variablesReference"]
+            .as_u64()
+            .and_then(|id| session.variables.get((id as usize).checked_sub(1)?))
+            .ok_or("Unknown variables reference")?
+            .clone();
+        let variables = variables
+            .into_iter()
+            .map(|variable| {
+                json!({
+                    "name": variable.name,
+                    "value": variable.value,
+                    "type": variable.r#type,
+                    "variablesReference": session.alloc_variables(variable.children),
+                })
+            })
+            .collect::<Vec<_>>();
+        Ok(json!({ "variables": variables }))
+    }
+
+    fn source(&self, arguments: &Value) -> Result<Value, String> {
+        let module = arguments["sourceReference"]
+            .as_u64()
+            .or_else(|| arguments["source"]["sourceReference"].as_u64())
+            .and_then(|id| self.modules.get((id as usize).checked_sub(1)?))
+            .ok_or("Unknown source")?;
+        Ok(json!({ "content": module.source }))
+    }
+
+    fn source_json(&self, session: &Session, module: ModuleID) -> Value {
+        let name = format!("{}.strk", self.modules[module].path);
+        match session.client_paths.get(&module) {
+            Some(path) => json!({ "name": name, "path": path }),
+            None => json!({ "name": name, "sourceReference": module + 1 }),
+        }
+    }
+
+    fn module_for_source(&self, source: &Value) -> Option<ModuleID> {
+        if let Some(id) = source["sourceReference"].as_u64().filter(|id| *id > 0) {
+            return Some(id as usize - 1).filter(|id| *id < self.modules.len());
+        }
+        [&source["path"], &source["name"]]
+            .into_iter()
+            .filter_map(|path| Path::new(path.as_str()?).file_stem()?.to_str())
+            .find_map(|stem| self.modules.iter().position(|module| module.path == stem))
+    }
+}
+
+impl Session {
+    fn send(&mut self, mut message: Value) {
+        message["seq"] = self.seq.into();
+        self.seq += 1;
+        // If the client is gone the reading side will notice and end the session
+        let _ = write_message(&mut self.writer, &message);
+    }
+
+    fn event(&mut self, event: &str, body: Value) {
+        let mut message = json!({ "type": "event", "event": event });
+        if !body.is_null() {
+            message["body"] = body;
+        }
+        self.send(message);
+    }
+
+    /// Returns 0, meaning no children, for empty lists
+    fn alloc_variables(&mut self, variables: Vec<Variable>) -> usize {
+        if variables.is_empty() {
+            return 0;
+        }
+        self.variables.push(variables);
+        self.variables.len()
+    }
+
+    fn line_offset(&self) -> usize {
+        self.lines_start_at_1.into()
+    }
+
+    fn column_offset(&self) -> usize {
+        self.columns_start_at_1.into()
+    }
+}
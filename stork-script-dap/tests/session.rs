@@ -0,0 +1,209 @@
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    thread,
+};
+
+use bevy_ecs::prelude::ReflectComponent;
+use bevy_ecs::{component::Component, reflect::AppTypeRegistry, world::World};
+use bevy_reflect::Reflect;
+use serde_json::{json, Value};
+use stork_script_bevy::vm_module_index::VMModuleIndex;
+use stork_script_core::module_index::Module;
+
+#[derive(Debug, Reflect, Component, Default, PartialEq)]
+#[reflect(Component)]
+pub struct Transform {
+    pub translation: Translation,
+}
+
+#[derive(Debug, Reflect, Default, PartialEq)]
+pub struct Translation {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+const SOURCE: &str = "use std
+
+sys move_right {
+    query entity {
+        let speed = 5;
+        entity[Transform].translation.x += speed;
+    }
+}
+";
+
+fn create_world() -> World {
+    let mut world = World::new();
+    world.init_resource::<AppTypeRegistry>();
+    {
+        let mut registry = world.resource::<AppTypeRegistry>().write();
+        registry.register::<()>();
+        registry.register::<Transform>();
+    }
+    world.spawn(Transform::default());
+    world.spawn(Transform::default());
+
+    world.init_resource::<VMModuleIndex>();
+    world.resource_scope::<VMModuleIndex, _>(|world, mut vm| {
+        vm.index
+            .add_module("main", |module_id| Module::from_source(SOURCE, module_id))
+            .unwrap();
+        vm.add_std(world);
+        if let Err(err) = vm.compile(world) {
+            vm.index.print_errors();
+            panic!("{err}");
+        }
+    });
+    world
+}
+
+struct Client {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    seq: u64,
+    events: VecDeque<Value>,
+}
+
+impl Client {
+    fn read(&mut self) -> Value {
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            self.reader.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(length) = line.strip_prefix("Content-Length: ") {
+                content_length = length.parse().unwrap();
+            }
+        }
+        let mut body = vec![0; content_length];
+        self.reader.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        self.seq += 1;
+        let body = json!({
+            "seq": self.seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        })
+        .to_string();
+        write!(self.writer, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+
+        loop {
+            let message = self.read();
+            if message["type"] == "response" && message["request_seq"] == self.seq {
+                assert_eq!(message["success"], true, "{message}");
+                return message["body"].clone();
+            }
+            self.events.push_back(message);
+        }
+    }
+
+    fn event(&mut self, event: &str) -> Value {
+        loop {
+            let message = self.events.pop_front().unwrap_or_else(|| self.read());
+            if message["event"] == event {
+                return message["body"].clone();
+            }
+        }
+    }
+
+    fn top_frame(&mut self) -> Value {
+        let trace = self.request("stackTrace", json!({ "threadId": 1 }));
+        trace["stackFrames"][0].clone()
+    }
+
+    fn scope(&mut self, frame: &Value, name: &str) -> Vec<Value> {
+        let scopes = self.request("scopes", json!({ "frameId": frame["id"] }));
+        let scope = scopes["scopes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|scope| scope["name"] == name)
+            .unwrap()
+            .clone();
+        let variables = self.request(
+            "variables",
+            json!({ "variablesReference": scope["variablesReference"] }),
+        );
+        variables["variables"].as_array().unwrap().clone()
+    }
+}
+
+#[test]
+fn breakpoints_and_stepping() {
+    let mut world = create_world();
+    let addr = stork_script_dap::attach_tcp(&mut world, "127.0.0.1:0").unwrap();
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let mut client = Client {
+        reader: BufReader::new(stream.try_clone().unwrap()),
+        writer: stream,
+        seq: 0,
+        events: VecDeque::new(),
+    };
+
+    client.request("initialize", json!({ "adapterID": "stork" }));
+    client.event("initialized");
+    let breakpoints = client.request(
+        "setBreakpoints",
+        json!({
+            "source": { "path": "/scripts/main.strk" },
+            "breakpoints": [{ "line": 6 }],
+        }),
+    );
+    assert_eq!(breakpoints["breakpoints"][0]["verified"], true);
+    client.request("configurationDone", json!({}));
+
+    let system = world
+        .resource::<VMModuleIndex>()
+        .get_system_id("main", "move_right")
+        .unwrap();
+    let app = thread::spawn(move || {
+        world.run_system(system).unwrap();
+        world
+    });
+
+    let stopped = client.event("stopped");
+    assert_eq!(stopped["reason"], "breakpoint");
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }));
+    let frames = trace["stackFrames"].as_array().unwrap();
+    assert_eq!(frames[0]["name"], "query entity");
+    assert_eq!(frames[0]["line"], 6);
+    assert_eq!(frames[0]["source"]["path"], "/scripts/main.strk");
+    assert_eq!(frames[1]["name"], "sys move_right");
+    assert_eq!(frames[1]["line"], 4);
+
+    let frame = frames[0].clone();
+    let locals = client.scope(&frame, "Locals");
+    let speed = locals.iter().find(|v| v["name"] == "speed").unwrap();
+    assert_eq!(speed["value"], "5.0");
+    assert!(locals.iter().any(|v| v["name"] == "entity"));
+    let entity = client.scope(&frame, "Entity");
+    let transform = entity.iter().find(|v| v["name"] == "Transform").unwrap();
+    assert_ne!(transform["variablesReference"], 0);
+
+    // Stepping over the last statement of the query goes to the next entity
+    client.request("next", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["reason"], "step");
+    assert_eq!(client.top_frame()["line"], 5);
+
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.event("stopped")["reason"], "breakpoint");
+    assert_eq!(client.top_frame()["line"], 6);
+
+    client.request("disconnect", json!({}));
+    let mut world = app.join().unwrap();
+
+    for transform in world.query::<&Transform>().iter(&world) {
+        assert_eq!(transform.translation.x, 5.);
+    }
+}
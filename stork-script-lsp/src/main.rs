@@ -1,20 +1,78 @@
 mod server;
+#[path = "workspace/workspace.rs"]
+mod workspace;
+
+use std::{net::SocketAddr, path::PathBuf, process::ExitCode};
 
 use async_std::{io, net::TcpListener, stream::StreamExt as _, task};
+use clap::Parser;
+use stork_script_core::schema::Schema;
+
+#[derive(Parser)]
+#[command(
+    name = "stork-script-lsp",
+    version,
+    about = "Language server for stork scripts"
+)]
+struct Args {
+    /// Talk to the editor over stdin and stdout, the default
+    #[arg(long, conflicts_with_all = ["port", "listen"])]
+    stdio: bool,
+    /// Listen for editors on this port on localhost
+    #[arg(long, conflicts_with = "listen")]
+    port: Option<u16>,
+    /// Listen for editors on this address, like `127.0.0.1:50022`
+    #[arg(long)]
+    listen: Option<SocketAddr>,
+    /// A schema exported from the game with `VMModuleIndex::export_schema`, so
+    /// its types are known
+    #[arg(long)]
+    schema: Option<PathBuf>,
+}
+
+fn main() -> io::Result<ExitCode> {
+    let args = Args::parse();
+    let schema = match args.schema.as_deref().map(Schema::load).transpose() {
+        Ok(schema) => schema,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+    let address = match args {
+        Args { stdio: true, .. } => None,
+        Args { listen, port, .. } => {
+            listen.or(port.map(|port| SocketAddr::from(([127, 0, 0, 1], port))))
+        }
+    };
 
-fn main() -> io::Result<()> {
     task::block_on(async {
-        let listener = TcpListener::bind("127.0.0.1:50022").await?;
-        println!("Listening on {}", listener.local_addr()?);
+        let Some(address) = address else {
+            // stdout belongs to the protocol, anything else goes to stderr
+            let shut_down = server::serve(io::stdin(), io::stdout(), schema)
+                .await
+                .map_err(io::Error::other)?;
+            // The protocol asks for 1 when the client exits without shutting down first
+            return Ok(if shut_down {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            });
+        };
 
-        let mut incoming = listener.incoming();
+        let listener = TcpListener::bind(address).await?;
+        eprintln!("Listening on {}", listener.local_addr()?);
 
+        let mut incoming = listener.incoming();
         while let Some(stream) = incoming.next().await {
             let stream = stream?;
+            let schema = schema.clone();
             task::spawn(async move {
-                server::spawn(&stream).await;
+                if let Err(err) = server::serve(&stream, &stream, schema).await {
+                    eprintln!("Connection failed: {err}");
+                }
             });
         }
-        io::Result::<()>::Ok(())
+        Ok(ExitCode::SUCCESS)
     })
 }
@@ -1,23 +1,53 @@
-use std::ops::ControlFlow;
+use std::{
+    ops::ControlFlow,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 use async_lsp::{
     client_monitor::ClientProcessMonitorLayer,
     concurrency::ConcurrencyLayer,
-    lsp_types::{InitializeParams, InitializeResult, ServerCapabilities},
+    lsp_types::{
+        CodeActionParams, CodeActionProviderCapability, CodeActionResponse, CompletionOptions,
+        CompletionParams, CompletionResponse, DidChangeTextDocumentParams,
+        DidCloseTextDocumentParams, DidOpenTextDocumentParams, DocumentSymbolParams,
+        DocumentSymbolResponse, FoldingRange, FoldingRangeParams, FoldingRangeProviderCapability,
+        GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverParams, HoverProviderCapability,
+        InitializeParams, InitializeResult, InlayHint, InlayHintParams, Location, OneOf,
+        PrepareRenameResponse, PublishDiagnosticsParams, ReferenceParams, RenameOptions,
+        RenameParams, SelectionRange, SelectionRangeParams, SelectionRangeProviderCapability,
+        SemanticTokensFullOptions, SemanticTokensOptions, SemanticTokensParams,
+        SemanticTokensRangeParams, SemanticTokensRangeResult, SemanticTokensResult,
+        SemanticTokensServerCapabilities, ServerCapabilities, SignatureHelp, SignatureHelpOptions,
+        SignatureHelpParams, TextDocumentPositionParams, TextDocumentSyncCapability,
+        TextDocumentSyncKind, Url, WorkspaceEdit,
+    },
     panic::CatchUnwindLayer,
     router::Router,
     server::LifecycleLayer,
     tracing::TracingLayer,
-    ClientSocket, LanguageServer, ResponseError,
+    ClientSocket, ErrorCode, LanguageClient, LanguageServer, ResponseError,
 };
-use async_std::net::TcpStream;
-use futures::future::BoxFuture;
+use futures::{future::BoxFuture, AsyncRead, AsyncWrite};
+use stork_script_core::schema::Schema;
 use tower::ServiceBuilder;
 
-pub async fn spawn(stream: &TcpStream) {
+use crate::workspace::{semantic_tokens, Workspace};
+
+/// Serves one client until it sends `exit`, returns whether it asked to shut down first.
+pub async fn serve(
+    input: impl AsyncRead,
+    output: impl AsyncWrite,
+    schema: Option<Schema>,
+) -> async_lsp::Result<bool> {
+    let shut_down = Arc::new(AtomicBool::new(false));
     let (server, _) = async_lsp::MainLoop::new_server(|client| {
         let router = Router::from_language_server(ServerState {
             client: client.clone(),
+            workspace: Workspace::new(schema.clone()),
+            shut_down: shut_down.clone(),
         });
 
         ServiceBuilder::new()
@@ -29,12 +59,36 @@ pub async fn spawn(stream: &TcpStream) {
             .service(router)
     });
 
-    server.run_buffered(stream, stream).await.unwrap();
+    server.run_buffered(input, output).await?;
+    Ok(shut_down.load(Ordering::Relaxed))
 }
 
 pub struct ServerState {
-    #[expect(unused)]
     client: ClientSocket,
+    workspace: Workspace,
+    shut_down: Arc<AtomicBool>,
+}
+
+impl ServerState {
+    /// Publishes for every open document, and `closed` to clear its diagnostics.
+    fn publish_diagnostics(&mut self, closed: Option<Url>) -> ControlFlow<async_lsp::Result<()>> {
+        let diagnostics = self
+            .workspace
+            .documents()
+            .map(|url| (url.clone(), self.workspace.diagnostics(url)))
+            .chain(closed.map(|url| (url, Vec::new())))
+            .collect::<Vec<_>>();
+        for (uri, diagnostics) in diagnostics {
+            if let Err(err) = self.client.publish_diagnostics(PublishDiagnosticsParams {
+                uri,
+                diagnostics,
+                version: None,
+            }) {
+                return ControlFlow::Break(Err(err));
+            }
+        }
+        ControlFlow::Continue(())
+    }
 }
 
 impl LanguageServer for ServerState {
@@ -43,15 +97,231 @@ impl LanguageServer for ServerState {
 
     fn initialize(
         &mut self,
-        _: InitializeParams,
+        params: InitializeParams,
     ) -> BoxFuture<'static, Result<InitializeResult, Self::Error>> {
+        #[allow(deprecated)]
+        let root = params
+            .workspace_folders
+            .and_then(|folders| folders.into_iter().next())
+            .map(|folder| folder.uri)
+            .or(params.root_uri);
+        if let Some(root) = root.and_then(|root| root.to_file_path().ok()) {
+            self.workspace.set_root(root);
+        }
         Box::pin(async move {
             Ok(InitializeResult {
                 capabilities: ServerCapabilities {
+                    text_document_sync: Some(TextDocumentSyncCapability::Kind(
+                        TextDocumentSyncKind::INCREMENTAL,
+                    )),
+                    definition_provider: Some(OneOf::Left(true)),
+                    references_provider: Some(OneOf::Left(true)),
+                    completion_provider: Some(CompletionOptions {
+                        trigger_characters: Some(vec!["[".to_string(), ".".to_string()]),
+                        ..CompletionOptions::default()
+                    }),
+                    hover_provider: Some(HoverProviderCapability::Simple(true)),
+                    signature_help_provider: Some(SignatureHelpOptions {
+                        trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+                        ..SignatureHelpOptions::default()
+                    }),
+                    code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                    rename_provider: Some(OneOf::Right(RenameOptions {
+                        prepare_provider: Some(true),
+                        work_done_progress_options: Default::default(),
+                    })),
+                    document_symbol_provider: Some(OneOf::Left(true)),
+                    inlay_hint_provider: Some(OneOf::Left(true)),
+                    folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+                    selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+                    semantic_tokens_provider: Some(
+                        SemanticTokensServerCapabilities::SemanticTokensOptions(
+                            SemanticTokensOptions {
+                                legend: semantic_tokens::legend(),
+                                range: Some(true),
+                                full: Some(SemanticTokensFullOptions::Bool(true)),
+                                ..SemanticTokensOptions::default()
+                            },
+                        ),
+                    ),
                     ..ServerCapabilities::default()
                 },
                 server_info: None,
             })
         })
     }
+
+    fn shutdown(&mut self, (): ()) -> BoxFuture<'static, Result<(), Self::Error>> {
+        self.shut_down.store(true, Ordering::Relaxed);
+        Box::pin(async { Ok(()) })
+    }
+
+    fn definition(
+        &mut self,
+        params: GotoDefinitionParams,
+    ) -> BoxFuture<'static, Result<Option<GotoDefinitionResponse>, Self::Error>> {
+        let params = params.text_document_position_params;
+        let location = self
+            .workspace
+            .definition(&params.text_document.uri, params.position);
+        Box::pin(async move { Ok(location.map(GotoDefinitionResponse::Scalar)) })
+    }
+
+    fn references(
+        &mut self,
+        params: ReferenceParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<Location>>, Self::Error>> {
+        let position = params.text_document_position;
+        let locations = self.workspace.references(
+            &position.text_document.uri,
+            position.position,
+            params.context.include_declaration,
+        );
+        Box::pin(async move { Ok(locations) })
+    }
+
+    fn hover(
+        &mut self,
+        params: HoverParams,
+    ) -> BoxFuture<'static, Result<Option<Hover>, Self::Error>> {
+        let params = params.text_document_position_params;
+        let hover = self
+            .workspace
+            .hover(&params.text_document.uri, params.position);
+        Box::pin(async move { Ok(hover) })
+    }
+
+    fn completion(
+        &mut self,
+        params: CompletionParams,
+    ) -> BoxFuture<'static, Result<Option<CompletionResponse>, Self::Error>> {
+        let position = params.text_document_position;
+        let items = self
+            .workspace
+            .completions(&position.text_document.uri, position.position);
+        Box::pin(async move { Ok(items.map(CompletionResponse::Array)) })
+    }
+
+    fn signature_help(
+        &mut self,
+        params: SignatureHelpParams,
+    ) -> BoxFuture<'static, Result<Option<SignatureHelp>, Self::Error>> {
+        let params = params.text_document_position_params;
+        let help = self
+            .workspace
+            .signature_help(&params.text_document.uri, params.position);
+        Box::pin(async move { Ok(help) })
+    }
+
+    fn code_action(
+        &mut self,
+        params: CodeActionParams,
+    ) -> BoxFuture<'static, Result<Option<CodeActionResponse>, Self::Error>> {
+        let actions = self
+            .workspace
+            .code_actions(&params.text_document.uri, params.range);
+        Box::pin(async move { Ok(actions) })
+    }
+
+    fn prepare_rename(
+        &mut self,
+        params: TextDocumentPositionParams,
+    ) -> BoxFuture<'static, Result<Option<PrepareRenameResponse>, Self::Error>> {
+        let range = self
+            .workspace
+            .prepare_rename(&params.text_document.uri, params.position);
+        Box::pin(async move {
+            range
+                .map(|range| range.map(PrepareRenameResponse::Range))
+                .map_err(|message| ResponseError::new(ErrorCode::REQUEST_FAILED, message))
+        })
+    }
+
+    fn rename(
+        &mut self,
+        params: RenameParams,
+    ) -> BoxFuture<'static, Result<Option<WorkspaceEdit>, Self::Error>> {
+        let position = params.text_document_position;
+        let edit = self.workspace.rename(
+            &position.text_document.uri,
+            position.position,
+            &params.new_name,
+        );
+        Box::pin(async move {
+            edit.map_err(|message| ResponseError::new(ErrorCode::REQUEST_FAILED, message))
+        })
+    }
+
+    fn semantic_tokens_full(
+        &mut self,
+        params: SemanticTokensParams,
+    ) -> BoxFuture<'static, Result<Option<SemanticTokensResult>, Self::Error>> {
+        let tokens = self
+            .workspace
+            .semantic_tokens(&params.text_document.uri, None);
+        Box::pin(async move { Ok(tokens.map(SemanticTokensResult::Tokens)) })
+    }
+
+    fn semantic_tokens_range(
+        &mut self,
+        params: SemanticTokensRangeParams,
+    ) -> BoxFuture<'static, Result<Option<SemanticTokensRangeResult>, Self::Error>> {
+        let tokens = self
+            .workspace
+            .semantic_tokens(&params.text_document.uri, Some(params.range));
+        Box::pin(async move { Ok(tokens.map(SemanticTokensRangeResult::Tokens)) })
+    }
+
+    fn document_symbol(
+        &mut self,
+        params: DocumentSymbolParams,
+    ) -> BoxFuture<'static, Result<Option<DocumentSymbolResponse>, Self::Error>> {
+        let symbols = self.workspace.document_symbols(&params.text_document.uri);
+        Box::pin(async move { Ok(symbols.map(DocumentSymbolResponse::Nested)) })
+    }
+
+    fn folding_range(
+        &mut self,
+        params: FoldingRangeParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<FoldingRange>>, Self::Error>> {
+        let ranges = self.workspace.folding_ranges(&params.text_document.uri);
+        Box::pin(async move { Ok(ranges) })
+    }
+
+    fn selection_range(
+        &mut self,
+        params: SelectionRangeParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<SelectionRange>>, Self::Error>> {
+        let ranges = self
+            .workspace
+            .selection_ranges(&params.text_document.uri, params.positions);
+        Box::pin(async move { Ok(ranges) })
+    }
+
+    fn inlay_hint(
+        &mut self,
+        params: InlayHintParams,
+    ) -> BoxFuture<'static, Result<Option<Vec<InlayHint>>, Self::Error>> {
+        let hints = self
+            .workspace
+            .inlay_hints(&params.text_document.uri, params.range);
+        Box::pin(async move { Ok(hints) })
+    }
+
+    fn did_open(&mut self, params: DidOpenTextDocumentParams) -> Self::NotifyResult {
+        let document = params.text_document;
+        self.workspace.open(document.uri, document.text);
+        self.publish_diagnostics(None)
+    }
+
+    fn did_change(&mut self, params: DidChangeTextDocumentParams) -> Self::NotifyResult {
+        self.workspace
+            .change(&params.text_document.uri, params.content_changes);
+        self.publish_diagnostics(None)
+    }
+
+    fn did_close(&mut self, params: DidCloseTextDocumentParams) -> Self::NotifyResult {
+        self.workspace.close(&params.text_document.uri);
+        self.publish_diagnostics(Some(params.text_document.uri))
+    }
 }
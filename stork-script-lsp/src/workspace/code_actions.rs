@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+
+use async_lsp::lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, Range, TextEdit, Url, WorkspaceEdit,
+};
+
+use super::Workspace;
+
+impl Workspace {
+    /// The fixes of the diagnostics overlapping `range`.
+    pub fn code_actions(&self, url: &Url, range: Range) -> Option<Vec<CodeActionOrCommand>> {
+        let module_id = self.module_id(url)?;
+        Some(
+            self.index
+                .reports(module_id)
+                .filter(|report| {
+                    self.range(&report.span()).is_some_and(|report_range| {
+                        report_range.start <= range.end && range.start <= report_range.end
+                    })
+                })
+                .flat_map(|report| {
+                    let diagnostic = self.diagnostic(module_id, report);
+                    report.fixes.iter().filter_map(move |fix| {
+                        let mut changes = HashMap::<Url, Vec<TextEdit>>::new();
+                        for edit in &fix.edits {
+                            let location = self.location(&edit.span)?;
+                            changes.entry(location.uri).or_default().push(TextEdit {
+                                range: location.range,
+                                new_text: edit.text.clone(),
+                            });
+                        }
+                        Some(CodeActionOrCommand::CodeAction(CodeAction {
+                            title: fix.title.clone(),
+                            kind: Some(CodeActionKind::QUICKFIX),
+                            diagnostics: Some(vec![diagnostic.clone()]),
+                            edit: Some(WorkspaceEdit {
+                                changes: Some(changes),
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        }))
+                    })
+                })
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,207 @@
+use std::ops::Range;
+
+use async_lsp::lsp_types::{CompletionItem, CompletionItemKind, Position, Url};
+use stork_script_core::{
+    cst::Token,
+    hir::{Expr, GlobalIdx, Identifier, Node},
+    module_index::{cache::ResolvedDefinition, ModuleID},
+    passes::type_resolution::{InnerResolvedType, ResolvedType},
+};
+
+use super::{offset, Workspace};
+
+impl Workspace {
+    /// Components after `entity[`, resources after `[`, fields after `.` and
+    /// otherwise the locals, systems and functions in scope.
+    pub fn completions(&self, url: &Url, position: Position) -> Option<Vec<CompletionItem>> {
+        let module_id = self.module_id(url)?;
+        let offset = offset(
+            self.index.modules.get_ref(module_id).source.text(),
+            position,
+        );
+        let syntax = self.syntax(module_id)?;
+
+        // Skip the identifier being typed and any trivia before it
+        let mut token = syntax
+            .token_at_offset(offset.try_into().ok()?)
+            .left_biased()?;
+        if token.kind() == Token::IDENT {
+            token = token.prev_token()?;
+        }
+        while matches!(
+            token.kind(),
+            Token::WHITE_SPACE | Token::NEW_LINE | Token::COMMENT
+        ) {
+            token = token.prev_token()?;
+        }
+
+        let parent = token.parent()?;
+        let mut items = match (token.kind(), parent.kind()) {
+            (Token::LBRACKET, Token::ComponentAccess) => self
+                .scope_at(module_id, offset)
+                .filter(|(_, definition)| self.is_ecs_type(*definition, false))
+                .map(|(name, definition)| self.item(name, definition, CompletionItemKind::CLASS))
+                .collect(),
+            (Token::LBRACKET, Token::ResourceAccess) => self
+                .scope_at(module_id, offset)
+                .filter(|(_, definition)| self.is_ecs_type(*definition, true))
+                .map(|(name, definition)| self.item(name, definition, CompletionItemKind::CLASS))
+                .collect(),
+            (Token::DOT, _) => {
+                let range: Range<usize> = parent.text_range().into();
+                let base = self
+                    .index
+                    .modules
+                    .get_ref(module_id)
+                    .spans
+                    .iter()
+                    .find_map(
+                        |(idx, ptr)| match self.index.modules.get_node((module_id, idx)) {
+                            Node::Expr(Expr::MemberAccess { base, .. })
+                                if Range::<usize>::from(ptr.text_range()) == range =>
+                            {
+                                Some(GlobalIdx::new(module_id, *base))
+                            }
+                            _ => None,
+                        },
+                    )?;
+                let InnerResolvedType::Struct { fields } = self.expr_type(base)?.inner else {
+                    return None;
+                };
+                fields
+                    .into_iter()
+                    .map(|(name, r#type)| CompletionItem {
+                        label: name,
+                        kind: Some(CompletionItemKind::FIELD),
+                        detail: Some(r#type.plain()),
+                        ..Default::default()
+                    })
+                    .collect()
+            }
+            _ => self
+                .scope_at(module_id, offset)
+                .filter_map(|(name, definition)| {
+                    let kind = match self.index.modules.get_node(definition) {
+                        Node::Expr(_) => CompletionItemKind::VARIABLE,
+                        Node::System(_) => CompletionItemKind::FUNCTION,
+                        Node::Builtin {
+                            r#type:
+                                ResolvedType {
+                                    inner: InnerResolvedType::Function { .. },
+                                    ..
+                                },
+                            ..
+                        } => CompletionItemKind::FUNCTION,
+                        _ => return None,
+                    };
+                    Some(self.item(name, definition, kind))
+                })
+                .collect::<Vec<_>>(),
+        };
+        items.sort_by(|a, b| a.label.cmp(&b.label));
+        Some(items)
+    }
+
+    /// The names visible at `offset`, from the innermost block around it.
+    fn scope_at(
+        &self,
+        module_id: ModuleID,
+        offset: usize,
+    ) -> impl Iterator<Item = (String, GlobalIdx)> + '_ {
+        let module = self.index.modules.get_ref(module_id);
+        let block = module
+            .spans
+            .iter()
+            .filter(|(idx, ptr)| {
+                let range: Range<usize> = ptr.text_range().into();
+                matches!(module.nodes[*idx], Node::Expr(Expr::Block(_)))
+                    && range.start < offset
+                    && offset <= range.end
+            })
+            .min_by_key(|(_, ptr)| ptr.text_range().len())
+            .map(|(idx, _)| GlobalIdx::new(module_id, idx));
+
+        block
+            .map(|block| self.index.cache.visible_names(block))
+            .into_iter()
+            .flatten()
+            .filter_map(move |(identifier, ResolvedDefinition(definition))| {
+                let Identifier::Name(name) = identifier else {
+                    return None;
+                };
+                // Locals declared further down aren't visible yet
+                if definition.module() == module_id
+                    && matches!(self.index.modules.get_node(*definition), Node::Expr(_))
+                    && module
+                        .spans
+                        .get(definition.idx())
+                        .is_some_and(|ptr| usize::from(ptr.text_range().start()) >= offset)
+                {
+                    return None;
+                }
+                Some((name.clone(), *definition))
+            })
+    }
+
+    /// Whether `definition` is a component, or a resource when `resource`. Builtins
+    /// from the type registry can be both.
+    fn is_ecs_type(&self, definition: GlobalIdx, resource: bool) -> bool {
+        match self.index.modules.get_node(definition) {
+            Node::Builtin { info, .. } if resource => info.resource,
+            Node::Builtin { info, .. } => info.component,
+            Node::Component(_) => !resource,
+            Node::Resource(_) => resource,
+            _ => false,
+        }
+    }
+
+    /// The type of an expression, falling back on its definition when type
+    /// resolution gave up, like in unfinished code.
+    fn expr_type(&self, node: GlobalIdx) -> Option<ResolvedType> {
+        if let Some(r#type) = self
+            .index
+            .cache
+            .types
+            .get(node)
+            .filter(|r#type| r#type.inner != InnerResolvedType::Poison)
+        {
+            return Some(r#type);
+        }
+        let definition = match self.index.modules.get_node(node) {
+            Node::Expr(Expr::ComponentAccess { component, .. }) => {
+                self.index.cache.names.get((node.module(), *component))?
+            }
+            Node::Expr(Expr::ResourceAccess { resource }) => {
+                self.index.cache.names.get((node.module(), *resource))?
+            }
+            Node::Expr(Expr::Identifier(_)) => self.index.cache.names.get(node)?,
+            _ => return None,
+        };
+        self.index.cache.types.get(definition.definition())
+    }
+
+    fn item(
+        &self,
+        name: String,
+        definition: GlobalIdx,
+        kind: CompletionItemKind,
+    ) -> CompletionItem {
+        // A query's type is its block's, the name it declares is an entity
+        let detail = match self.index.modules.get_node(definition) {
+            Node::Expr(Expr::Query { .. }) => Some(InnerResolvedType::Entity.plain()),
+            _ => self
+                .index
+                .cache
+                .types
+                .get(definition)
+                .filter(|r#type| r#type.inner != InnerResolvedType::Poison)
+                .map(|r#type| r#type.inner.plain()),
+        };
+        CompletionItem {
+            label: name,
+            kind: Some(kind),
+            detail,
+            ..Default::default()
+        }
+    }
+}
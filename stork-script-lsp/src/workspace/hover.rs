@@ -0,0 +1,172 @@
+use std::collections::BTreeSet;
+
+use async_lsp::lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind, Position, Range, Url};
+use stork_script_core::{
+    hir::{Expr, GlobalIdx, Identifier, Node, System},
+    passes::{
+        borrow_resolution::{ComponentEffectKind, ResolvedEffect},
+        type_resolution::InnerResolvedType,
+    },
+};
+
+use super::Workspace;
+
+impl Workspace {
+    /// The effects of systems and queries, the type of anything else.
+    pub fn hover(&self, url: &Url, position: Position) -> Option<Hover> {
+        let node = self.node_at(url, position)?;
+        let mut range = self.node_range(node);
+        let value = match self.index.modules.get_node(node) {
+            Node::System(System { ident, .. }) => {
+                let title = match ident {
+                    Some(ident) => format!("sys {ident}"),
+                    None => "sys".to_string(),
+                };
+                range = self.name_location(node).map(|location| location.range);
+                self.effects_summary(node, title)
+            }
+            Node::Expr(Expr::Query { entity, .. }) => {
+                self.effects_summary(node, format!("query {entity}"))
+            }
+            _ => self.type_summary(node)?,
+        };
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range,
+        })
+    }
+
+    pub fn node_range(&self, node: GlobalIdx) -> Option<Range> {
+        let ptr = self
+            .index
+            .modules
+            .get_ref(node.module())
+            .spans
+            .get(node.idx())?;
+        self.range(&(node.module(), ptr.text_range().into()))
+    }
+
+    fn type_summary(&self, node: GlobalIdx) -> Option<String> {
+        let r#type = self.index.cache.types.get(node)?;
+        if matches!(
+            r#type.inner,
+            InnerResolvedType::Poison | InnerResolvedType::Recursion
+        ) {
+            return None;
+        }
+        let mut value = match (&r#type.inner, self.type_name(node)) {
+            (InnerResolvedType::Struct { .. }, Some(name)) => {
+                format!("```stork\n{name} {}\n```", r#type.inner.plain())
+            }
+            _ => format!("```stork\n{}\n```", r#type.inner.plain()),
+        };
+        if r#type.component_or_resource {
+            value.push_str("\n\nFrom the ECS");
+        }
+        Some(value)
+    }
+
+    /// The name of the struct type of `node`, when it's known.
+    fn type_name(&self, node: GlobalIdx) -> Option<String> {
+        let module_id = node.module();
+        let definition = match self.index.modules.get_node(node) {
+            Node::Expr(Expr::Struct {
+                ident: Identifier::Name(name),
+                ..
+            }) => return Some(name.clone()),
+            Node::Expr(Expr::ComponentAccess { component, .. }) => {
+                self.index.cache.names.get((module_id, *component))?
+            }
+            Node::Expr(Expr::ResourceAccess { resource }) => {
+                self.index.cache.names.get((module_id, *resource))?
+            }
+            Node::Expr(Expr::Identifier(_)) => self.index.cache.names.get(node)?,
+            _ => return self.definition_name(node),
+        };
+        match self.index.modules.get_node(definition.definition()) {
+            Node::Component(_) | Node::Resource(_) | Node::Builtin { .. } => {
+                self.definition_name(definition.definition())
+            }
+            _ => None,
+        }
+    }
+
+    fn definition_name(&self, node: GlobalIdx) -> Option<String> {
+        match self.index.modules.get_node(node) {
+            Node::System(System { ident, .. }) => ident.clone(),
+            Node::Component(typed_ident) | Node::Resource(typed_ident) => {
+                Some(typed_ident.ident.clone())
+            }
+            Node::Builtin {
+                identifier: Identifier::Name(name),
+                ..
+            } => Some(name.clone()),
+            Node::Expr(Expr::Query { entity, .. }) => Some(entity.clone()),
+            Node::Expr(Expr::Identifier(Identifier::Name(name))) => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    /// What `node` reads, writes and structurally modifies, as markdown.
+    fn effects_summary(&self, node: GlobalIdx, title: String) -> String {
+        let name = |node: GlobalIdx| {
+            format!(
+                "`{}`",
+                self.definition_name(node)
+                    .unwrap_or_else(|| "<unknown>".to_string())
+            )
+        };
+        let mut reads = BTreeSet::new();
+        let mut writes = BTreeSet::new();
+        let mut checks = BTreeSet::new();
+        let mut structural = BTreeSet::new();
+        for effect in self.index.cache.effects.get(node).unwrap_or_default() {
+            match effect {
+                ResolvedEffect::Access { component, kind } => {
+                    let (set, entity) = match kind {
+                        ComponentEffectKind::ReadResource => (&mut reads, None),
+                        ComponentEffectKind::WriteResource => (&mut writes, None),
+                        ComponentEffectKind::ReadComponent { entity } => (&mut reads, Some(entity)),
+                        ComponentEffectKind::WriteComponent { entity } => {
+                            (&mut writes, Some(entity))
+                        }
+                        ComponentEffectKind::HasComponent { entity } => (&mut checks, Some(entity)),
+                    };
+                    set.insert(match entity {
+                        Some(entity) => format!("{} of {}", name(component), name(entity)),
+                        None => format!("{} (resource)", name(component)),
+                    });
+                }
+                ResolvedEffect::Structural {
+                    entity: Some(entity),
+                } => {
+                    structural.insert(name(entity));
+                }
+                ResolvedEffect::Structural { entity: None } => {
+                    structural.insert("resources".to_string());
+                }
+            }
+        }
+
+        let mut value = format!("```stork\n{title}\n```");
+        let sections = [
+            ("Reads", reads),
+            ("Writes", writes),
+            ("Checks", checks),
+            ("Structurally modifies", structural),
+        ];
+        if sections.iter().all(|(_, set)| set.is_empty()) {
+            value.push_str("\n\nNo effects on the ECS");
+        }
+        for (label, set) in sections.into_iter().filter(|(_, set)| !set.is_empty()) {
+            value.push_str(&format!(
+                "\n\n{label}: {}",
+                set.into_iter().collect::<Vec<_>>().join(", ")
+            ));
+        }
+        value
+    }
+}
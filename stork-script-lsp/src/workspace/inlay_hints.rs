@@ -0,0 +1,109 @@
+use async_lsp::lsp_types::{InlayHint, InlayHintKind, InlayHintLabel, Position, Range, Url};
+use stork_script_core::{
+    hir::{Expr, GlobalIdx, Identifier, Node},
+    passes::type_resolution::InnerResolvedType,
+};
+
+use super::Workspace;
+
+impl Workspace {
+    /// The types of `let` bindings and query entities, and the parameter names
+    /// of builtin calls, within `range`.
+    pub fn inlay_hints(&self, url: &Url, range: Range) -> Option<Vec<InlayHint>> {
+        let module_id = self.module_id(url)?;
+        let module = self.index.modules.get_ref(module_id);
+        let mut hints = Vec::new();
+        for (idx, node) in module.nodes.iter() {
+            let node_idx = GlobalIdx::new(module_id, idx);
+            match node {
+                Node::Expr(Expr::Let { lvalue, .. }) => {
+                    let lvalue = GlobalIdx::new(module_id, *lvalue);
+                    if self
+                        .index
+                        .modules
+                        .get_node(lvalue)
+                        .as_expr_identifier()
+                        .is_none()
+                    {
+                        continue;
+                    }
+                    let Some(r#type) = self.index.cache.types.get(lvalue) else {
+                        continue;
+                    };
+                    if matches!(
+                        r#type.inner,
+                        InnerResolvedType::Poison | InnerResolvedType::Recursion
+                    ) {
+                        continue;
+                    }
+                    hints.extend(self.type_hint(lvalue, &r#type.inner));
+                }
+                Node::Expr(Expr::Query { .. }) => {
+                    hints.extend(self.type_hint(node_idx, &InnerResolvedType::Entity));
+                }
+                Node::Expr(Expr::FunctionCall { function, args }) => {
+                    let Some(definition) = self.index.cache.names.get((module_id, *function))
+                    else {
+                        continue;
+                    };
+                    let Node::Builtin {
+                        identifier: Identifier::Name(_),
+                        info,
+                        ..
+                    } = self.index.modules.get_node(definition.definition())
+                    else {
+                        continue;
+                    };
+                    for (arg, name) in args.iter().zip(&info.param_names) {
+                        let arg = GlobalIdx::new(module_id, *arg);
+                        let Some(name) = name else {
+                            continue;
+                        };
+                        // `f(name)` is clear enough without `f(name: name)`
+                        if matches!(
+                            self.index.modules.get_node(arg).as_expr_identifier(),
+                            Some(Identifier::Name(arg_name)) if arg_name == name
+                        ) {
+                            continue;
+                        }
+                        let Some(arg_range) = self.node_range(arg) else {
+                            continue;
+                        };
+                        hints.push(hint(
+                            arg_range.start,
+                            format!("{name}:"),
+                            InlayHintKind::PARAMETER,
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+        hints.retain(|hint| range.start <= hint.position && hint.position <= range.end);
+        hints.sort_by_key(|hint| hint.position);
+        Some(hints)
+    }
+
+    /// `: type` after the name of `node`.
+    fn type_hint(&self, node: GlobalIdx, r#type: &InnerResolvedType) -> Option<InlayHint> {
+        let location = self.name_location(node)?;
+        Some(hint(
+            location.range.end,
+            format!(": {}", r#type.plain()),
+            InlayHintKind::TYPE,
+        ))
+    }
+}
+
+fn hint(position: Position, label: String, kind: InlayHintKind) -> InlayHint {
+    InlayHint {
+        position,
+        label: InlayHintLabel::String(label),
+        kind: Some(kind),
+        text_edits: None,
+        tooltip: None,
+        padding_left: None,
+        padding_right: Some(kind == InlayHintKind::PARAMETER),
+        data: None,
+    }
+}
@@ -0,0 +1,197 @@
+use std::ops::Range;
+
+use async_lsp::lsp_types::{Location, Position, Url};
+use stork_script_core::{
+    cst::{SyntaxNode, Token},
+    hir::{Expr, GlobalIdx, Identifier, ImportKind, Node, System},
+    module_index::ModuleID,
+};
+
+use super::{offset, Workspace};
+
+/// Something that can be jumped to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Node(GlobalIdx),
+    Module(ModuleID),
+}
+
+impl Workspace {
+    /// The innermost node at `position`.
+    pub fn node_at(&self, url: &Url, position: Position) -> Option<GlobalIdx> {
+        let module_id = self.module_id(url)?;
+        let module = self.index.modules.get_ref(module_id);
+        let offset = offset(module.source.text(), position);
+        module
+            .spans
+            .iter()
+            .filter(|(_, ptr)| {
+                let range: Range<usize> = ptr.text_range().into();
+                range.start <= offset && offset <= range.end
+            })
+            .min_by_key(|(_, ptr)| ptr.text_range().len())
+            .map(|(idx, _)| (module_id, idx).into())
+    }
+
+    /// What `node` refers to, definitions refer to themselves.
+    pub fn target(&self, node: GlobalIdx) -> Option<Target> {
+        if let Some(definition) = self.index.cache.names.get(node) {
+            return Some(Target::Node(definition.definition()));
+        }
+        match self.index.modules.get_node(node) {
+            Node::Import(import) => self.index.modules.get_id(&import.path).map(Target::Module),
+            Node::System(_) | Node::Component(_) | Node::Resource(_) => Some(Target::Node(node)),
+            Node::Expr(Expr::Query { .. }) => Some(Target::Node(node)),
+            Node::Expr(Expr::Identifier(_)) if self.is_let_binding(node) => {
+                Some(Target::Node(node))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn definition(&self, url: &Url, position: Position) -> Option<Location> {
+        let target = self.target(self.node_at(url, position)?)?;
+        self.target_location(target)
+    }
+
+    /// Every use of what is at `position`, in all modules.
+    pub fn references(
+        &self,
+        url: &Url,
+        position: Position,
+        include_declaration: bool,
+    ) -> Option<Vec<Location>> {
+        let target = self.target(self.node_at(url, position)?)?;
+        let mut locations: Vec<_> = match target {
+            Target::Node(definition) => self
+                .index
+                .cache
+                .names
+                .iter()
+                .filter(|(_, resolved)| resolved.definition() == definition)
+                .filter_map(|(node, _)| self.name_location(*node))
+                .chain(self.import_name_locations(definition))
+                .collect(),
+            Target::Module(module_id) => self
+                .index
+                .modules
+                .all_ids()
+                .flat_map(|id| self.index.modules.top_level_ids(id))
+                .filter(|node| {
+                    matches!(
+                        self.index.modules.get_node(*node),
+                        Node::Import(import)
+                            if self.index.modules.get_id(&import.path) == Some(module_id)
+                    )
+                })
+                .filter_map(|node| self.name_location(node))
+                .collect(),
+        };
+        if include_declaration {
+            locations.extend(self.target_location(target));
+        }
+        locations.sort_by_key(|location| (location.uri.to_string(), location.range.start));
+        Some(locations)
+    }
+
+    /// `None` for targets in `std`.
+    pub fn target_location(&self, target: Target) -> Option<Location> {
+        match target {
+            Target::Node(node) => self.name_location(node),
+            Target::Module(module_id) => Some(Location {
+                uri: self.url(module_id)?.clone(),
+                range: Default::default(),
+            }),
+        }
+    }
+
+    /// The location of the identifier naming `node`, like the `a` in `let a = 1`
+    /// or the `Health` in `res Health: f32`.
+    pub fn name_location(&self, node: GlobalIdx) -> Option<Location> {
+        if let Some(Identifier::Operator(_)) =
+            self.index.modules.get_node(node).as_expr_identifier()
+        {
+            return None;
+        }
+        let ptr = self
+            .index
+            .modules
+            .get_ref(node.module())
+            .spans
+            .get(node.idx())?;
+        let syntax = ptr.to_node(&self.syntax(node.module())?);
+        let range = match self.index.modules.get_node(node) {
+            Node::System(System { ident: None, .. }) => None,
+            // The last segment of the module path
+            Node::Import(_) => syntax
+                .children_with_tokens()
+                .filter_map(|element| element.into_token())
+                .take_while(|token| token.kind() != Token::AS)
+                .filter(|token| token.kind() == Token::IDENT)
+                .last()
+                .map(|token| token.text_range().into()),
+            _ => first_ident(&syntax),
+        }
+        .unwrap_or_else(|| syntax.text_range().into());
+        self.location(&(node.module(), range))
+    }
+
+    /// The `name` in every `use path::{name}` or `use path::{name as alias}` of
+    /// `definition`, which aren't nodes themselves.
+    fn import_name_locations(&self, definition: GlobalIdx) -> Vec<Location> {
+        let Some(Identifier::Name(name)) =
+            self.index.modules.get_node(definition).item_identifier()
+        else {
+            return Vec::new();
+        };
+        let modules = &self.index.modules;
+        modules
+            .all_ids()
+            .flat_map(|id| modules.top_level_ids(id))
+            .filter(|node| {
+                matches!(
+                    modules.get_node(*node),
+                    Node::Import(import)
+                        if modules.get_id(&import.path) == Some(definition.module())
+                            && matches!(
+                                &import.kind,
+                                ImportKind::Names(names)
+                                    if names.iter().any(|imported| imported.name == name)
+                            )
+                )
+            })
+            .filter_map(|node| {
+                let ptr = modules.get_ref(node.module()).spans.get(node.idx())?;
+                let syntax = ptr.to_node(&self.syntax(node.module())?);
+                let token = syntax
+                    .descendants()
+                    .filter(|child| child.kind() == Token::ImportName)
+                    .filter_map(|child| child.first_token())
+                    .find(|token| token.kind() == Token::IDENT && token.text() == name)?;
+                self.location(&(node.module(), token.text_range().into()))
+            })
+            .collect()
+    }
+
+    fn is_let_binding(&self, node: GlobalIdx) -> bool {
+        self.index
+            .modules
+            .get_ref(node.module())
+            .nodes
+            .iter()
+            .any(|(_, other)| {
+                matches!(other, Node::Expr(Expr::Let { lvalue, .. }) if *lvalue == node.idx())
+            })
+    }
+}
+
+/// Skips module paths, so it's `b` in `a::b`.
+fn first_ident(node: &SyntaxNode) -> Option<Range<usize>> {
+    node.descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .find(|token| {
+            token.kind() == Token::IDENT
+                && token.next_token().map(|next| next.kind()) != Some(Token::COLONCOLON)
+        })
+        .map(|token| token.text_range().into())
+}
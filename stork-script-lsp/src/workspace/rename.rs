@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use async_lsp::lsp_types::{Location, Position, Range, TextEdit, Url, WorkspaceEdit};
+use stork_script_core::{
+    cst,
+    hir::{GlobalIdx, Identifier, Node, System},
+    module_index::cache::ResolvedDefinition,
+};
+
+use super::{navigation::Target, offset, Workspace};
+
+impl Workspace {
+    /// The range of the name at `position`, if it can be renamed.
+    pub fn prepare_rename(&self, url: &Url, position: Position) -> Result<Option<Range>, String> {
+        let Some(node) = self.node_at(url, position) else {
+            return Ok(None);
+        };
+        self.renamed_definition(node)?;
+        Ok(self.name_location(node).map(|location| location.range))
+    }
+
+    /// Renames the definition at `position` and all its uses, in every module.
+    pub fn rename(
+        &self,
+        url: &Url,
+        position: Position,
+        new_name: &str,
+    ) -> Result<Option<WorkspaceEdit>, String> {
+        let Some(node) = self.node_at(url, position) else {
+            return Ok(None);
+        };
+        let definition = self.renamed_definition(node)?;
+        if !cst::is_identifier(new_name) {
+            return Err(format!("`{new_name}` isn't a valid name"));
+        }
+        if let Some(collision) = self.collision(definition, new_name) {
+            let location = self
+                .name_location(collision)
+                .map(|location| format!(" at {}:{}", location.uri, location.range.start.line + 1))
+                .unwrap_or_default();
+            return Err(format!("`{new_name}` is already defined{location}"));
+        }
+
+        // Uses of an alias from `use path::{name as alias}` keep the alias
+        let old_name = self
+            .name_location(definition)
+            .and_then(|location| self.text_at(&location));
+        let mut changes = HashMap::<Url, Vec<TextEdit>>::new();
+        for location in self.references(url, position, true).unwrap_or_default() {
+            if self.text_at(&location) != old_name {
+                continue;
+            }
+            changes
+                .entry(location.uri)
+                .or_default()
+                .push(TextEdit::new(location.range, new_name.to_string()));
+        }
+        Ok(Some(WorkspaceEdit::new(changes)))
+    }
+
+    /// The definition `node` renames, or why it can't be renamed.
+    fn renamed_definition(&self, node: GlobalIdx) -> Result<GlobalIdx, String> {
+        let definition = match self.target(node) {
+            Some(Target::Node(definition)) => definition,
+            Some(Target::Module(_)) => return Err("Modules are named after their files".into()),
+            None => return Err("There is nothing to rename here".into()),
+        };
+        match self.index.modules.get_node(definition) {
+            _ if self.url(definition.module()).is_none() => {
+                Err("Builtins from `std` can't be renamed".into())
+            }
+            Node::Builtin { .. } => Err("Builtins can't be renamed".into()),
+            Node::System(System { ident: None, .. }) => Err("This system has no name".into()),
+            _ => Ok(definition),
+        }
+    }
+
+    fn text_at(&self, location: &Location) -> Option<String> {
+        let text = self
+            .index
+            .modules
+            .get_ref(self.module_id(&location.uri)?)
+            .source
+            .text();
+        let start = offset(text, location.range.start);
+        let end = offset(text, location.range.end);
+        Some(text[start..end].to_string())
+    }
+
+    /// Another definition named `new_name` visible wherever `definition` is.
+    fn collision(&self, definition: GlobalIdx, new_name: &str) -> Option<GlobalIdx> {
+        let new_name = Identifier::Name(new_name.to_string());
+        let definition = ResolvedDefinition(definition);
+        let cache = &self.index.cache;
+        let collision = |names: &HashMap<Identifier, ResolvedDefinition>| {
+            let other = names.get(&new_name)?;
+            (*other != definition && names.values().any(|visible| *visible == definition))
+                .then_some(other.definition())
+        };
+        // Only blocks declaring either name can have both in sight
+        let blocks = cache.scopes.iter().filter(|(_, scope)| {
+            scope.names.contains_key(&new_name)
+                || scope.names.values().any(|declared| *declared == definition)
+        });
+        cache
+            .module_scopes
+            .iter()
+            .find_map(|(_, names)| collision(names))
+            .or_else(|| {
+                blocks
+                    .map(|(block, _)| cache.visible_names(*block))
+                    .find_map(|names| collision(&names))
+            })
+    }
+}
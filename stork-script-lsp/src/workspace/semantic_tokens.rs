@@ -0,0 +1,136 @@
+use async_lsp::lsp_types::{
+    Range, SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens,
+    SemanticTokensLegend, Url,
+};
+use stork_script_core::{
+    hir::{Expr, GlobalIdx, Node},
+    passes::type_resolution::InnerResolvedType,
+};
+
+use super::{navigation::Target, Workspace};
+
+/// In [`legend`] order, the custom types have standard super types in the
+/// VS Code extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenType {
+    Component,
+    Resource,
+    System,
+    Entity,
+    Variable,
+    Function,
+    Type,
+}
+
+impl TokenType {
+    pub const ALL: [TokenType; 7] = [
+        TokenType::Component,
+        TokenType::Resource,
+        TokenType::System,
+        TokenType::Entity,
+        TokenType::Variable,
+        TokenType::Function,
+        TokenType::Type,
+    ];
+
+    fn semantic_token_type(self) -> SemanticTokenType {
+        match self {
+            TokenType::Component => SemanticTokenType::new("component"),
+            TokenType::Resource => SemanticTokenType::new("resource"),
+            TokenType::System => SemanticTokenType::new("system"),
+            TokenType::Entity => SemanticTokenType::new("entity"),
+            TokenType::Variable => SemanticTokenType::VARIABLE,
+            TokenType::Function => SemanticTokenType::FUNCTION,
+            TokenType::Type => SemanticTokenType::TYPE,
+        }
+    }
+}
+
+const DECLARATION: u32 = 1 << 0;
+const DEFAULT_LIBRARY: u32 = 1 << 1;
+
+pub fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TokenType::ALL.map(TokenType::semantic_token_type).to_vec(),
+        token_modifiers: vec![
+            SemanticTokenModifier::DECLARATION,
+            SemanticTokenModifier::DEFAULT_LIBRARY,
+        ],
+    }
+}
+
+impl Workspace {
+    /// Every identifier that resolves to something, within `range` if given.
+    pub fn semantic_tokens(&self, url: &Url, range: Option<Range>) -> Option<SemanticTokens> {
+        let module_id = self.module_id(url)?;
+        let mut tokens = self
+            .index
+            .modules
+            .get_ref(module_id)
+            .nodes
+            .iter()
+            .filter_map(|(idx, _)| {
+                let node = GlobalIdx::new(module_id, idx);
+                let (definition, modifiers) = match self.target(node)? {
+                    Target::Node(definition) if definition == node => (definition, DECLARATION),
+                    Target::Node(definition) => (definition, 0),
+                    Target::Module(_) => return None,
+                };
+                let (token_type, modifiers) = self.token_type(definition, modifiers)?;
+                Some((self.name_location(node)?.range, token_type, modifiers))
+            })
+            .filter(|(token_range, ..)| {
+                range.is_none_or(|range| {
+                    token_range.start < range.end && range.start < token_range.end
+                })
+            })
+            .collect::<Vec<_>>();
+        tokens.sort_by_key(|(range, ..)| range.start);
+        tokens.dedup_by_key(|(range, ..)| range.start);
+
+        let mut previous = Range::default();
+        let data = tokens
+            .into_iter()
+            .map(|(range, token_type, modifiers)| {
+                let delta_line = range.start.line - previous.start.line;
+                let delta_start = if delta_line == 0 {
+                    range.start.character - previous.start.character
+                } else {
+                    range.start.character
+                };
+                previous = range;
+                SemanticToken {
+                    delta_line,
+                    delta_start,
+                    length: range.end.character - range.start.character,
+                    token_type: token_type as u32,
+                    token_modifiers_bitset: modifiers,
+                }
+            })
+            .collect();
+        Some(SemanticTokens {
+            result_id: None,
+            data,
+        })
+    }
+
+    fn token_type(&self, definition: GlobalIdx, modifiers: u32) -> Option<(TokenType, u32)> {
+        Some(match self.index.modules.get_node(definition) {
+            Node::Component(_) => (TokenType::Component, modifiers),
+            Node::Resource(_) => (TokenType::Resource, modifiers),
+            Node::System(_) => (TokenType::System, modifiers),
+            Node::Expr(Expr::Query { .. }) => (TokenType::Entity, modifiers),
+            Node::Expr(_) => (TokenType::Variable, modifiers),
+            Node::Builtin { r#type, info, .. } => {
+                let token_type = match r#type.inner {
+                    InnerResolvedType::Function { .. } => TokenType::Function,
+                    _ if info.component => TokenType::Component,
+                    _ if info.resource => TokenType::Resource,
+                    _ => TokenType::Type,
+                };
+                (token_type, modifiers | DEFAULT_LIBRARY)
+            }
+            _ => return None,
+        })
+    }
+}
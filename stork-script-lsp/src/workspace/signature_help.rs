@@ -0,0 +1,137 @@
+use std::ops::Range;
+
+use async_lsp::lsp_types::{
+    ParameterInformation, ParameterLabel, Position, SignatureHelp, SignatureInformation, Url,
+};
+use stork_script_core::{
+    cst::{SyntaxNode, Token},
+    hir::{Expr, GlobalIdx, Identifier, Node},
+    passes::type_resolution::InnerResolvedType,
+};
+
+use super::{offset, Workspace};
+
+impl Workspace {
+    /// The signature of the innermost call around `position`, once past its `(`,
+    /// or of the operator expression around it.
+    pub fn signature_help(&self, url: &Url, position: Position) -> Option<SignatureHelp> {
+        let module_id = self.module_id(url)?;
+        let module = self.index.modules.get_ref(module_id);
+        let offset = offset(module.source.text(), position);
+        let syntax = self.syntax(module_id)?;
+
+        let token = syntax
+            .token_at_offset(offset.try_into().ok()?)
+            .left_biased()?;
+        let (node, function, active_parameter) = token.parent_ancestors().find_map(|node| {
+            let active_parameter = active_parameter(&node, offset)?;
+            let range: Range<usize> = node.text_range().into();
+            let function = module
+                .spans
+                .iter()
+                .find_map(|(idx, ptr)| match &module.nodes[idx] {
+                    Node::Expr(Expr::FunctionCall { function, .. })
+                        if Range::<usize>::from(ptr.text_range()) == range =>
+                    {
+                        Some(GlobalIdx::new(module_id, *function))
+                    }
+                    _ => None,
+                })?;
+            Some((node, function, active_parameter))
+        })?;
+
+        let name = match self.index.modules.get_node(function) {
+            Node::Expr(Expr::Identifier(Identifier::Name(name))) => name.clone(),
+            Node::Expr(Expr::Identifier(Identifier::Operator(_))) => operator_text(&node)?,
+            _ => return None,
+        };
+        let definition = self.index.cache.names.get(function)?.definition();
+        let (r#type, names) = match self.index.modules.get_node(definition) {
+            Node::Builtin { r#type, info, .. } => (r#type.inner.clone(), info.param_names.clone()),
+            _ => (self.index.cache.types.get(definition)?.inner, Vec::new()),
+        };
+        let InnerResolvedType::Function { params, ret } = r#type else {
+            return None;
+        };
+
+        // Offsets into the label are in UTF-16 code units
+        let mut label = format!("{name}(");
+        let mut parameters = Vec::new();
+        for (i, param) in params.iter().enumerate() {
+            if i > 0 {
+                label.push_str(", ");
+            }
+            let start = label.encode_utf16().count() as u32;
+            match names.get(i) {
+                Some(Some(name)) => label.push_str(&format!("{name}: {}", param.plain())),
+                _ => label.push_str(&param.plain()),
+            }
+            parameters.push(ParameterInformation {
+                label: ParameterLabel::LabelOffsets([start, label.encode_utf16().count() as u32]),
+                documentation: None,
+            });
+        }
+        label.push_str(&format!(") -> {}", ret.plain()));
+
+        // Past the last parameter there's nothing to highlight
+        let active_parameter = Some(active_parameter)
+            .filter(|active_parameter| (*active_parameter as usize) < params.len());
+        Some(SignatureHelp {
+            signatures: vec![SignatureInformation {
+                label,
+                documentation: None,
+                parameters: Some(parameters),
+                active_parameter,
+            }],
+            active_signature: Some(0),
+            active_parameter,
+        })
+    }
+}
+
+/// The index of the argument at `offset` if `node` is a call, from the commas
+/// before it, or an operator expression.
+fn active_parameter(node: &SyntaxNode, offset: usize) -> Option<u32> {
+    let tokens = node
+        .children_with_tokens()
+        .filter_map(|element| element.into_token())
+        .collect::<Vec<_>>();
+    let end = |kind| {
+        tokens
+            .iter()
+            .find(|token| token.kind() == kind)
+            .map(|token| usize::from(token.text_range().end()))
+    };
+    match node.kind() {
+        Token::Call => {
+            if offset < end(Token::LPAREN)? {
+                return None;
+            }
+            if end(Token::RPAREN).is_some_and(|rparen| offset >= rparen) {
+                return None;
+            }
+            Some(
+                tokens
+                    .iter()
+                    .filter(|token| {
+                        token.kind() == Token::COMMA
+                            && usize::from(token.text_range().end()) <= offset
+                    })
+                    .count() as u32,
+            )
+        }
+        Token::Prefix => Some(0),
+        Token::Infix => {
+            let operator = tokens.iter().find(|token| token.kind().is_infix_op())?;
+            Some((offset >= usize::from(operator.text_range().end())).into())
+        }
+        _ => None,
+    }
+}
+
+fn operator_text(node: &SyntaxNode) -> Option<String> {
+    node.children_with_tokens()
+        .filter_map(|element| element.into_token())
+        .find(|token| token.kind().is_infix_op() || token.kind().is_prefix_op())
+        .map(|token| token.text().to_string())
+}
@@ -0,0 +1,244 @@
+use std::ops::Range;
+
+use async_lsp::lsp_types::{
+    self, DocumentSymbol, FoldingRange, FoldingRangeKind, Position, SelectionRange, SymbolKind, Url,
+};
+use rowan::{ast::AstNode, TextRange};
+use stork_script_core::{
+    ast::{self, FieldType, Item, Type},
+    cst::{SyntaxNode, SyntaxToken, Token},
+};
+
+use super::{offset, position, Workspace};
+
+/// Outline, folding and selection ranges, from the syntax tree alone so they work
+/// on files that don't resolve.
+impl Workspace {
+    pub fn document_symbols(&self, url: &Url) -> Option<Vec<DocumentSymbol>> {
+        let (syntax, text) = self.document_syntax(url)?;
+        let root = ast::Root::cast(syntax)?;
+        Some(
+            root.items()
+                .filter_map(|item| {
+                    let syntax = item.syntax().clone();
+                    match item {
+                        Item::Component(component) => {
+                            field_symbol(text, &component.field()?, SymbolKind::STRUCT)
+                        }
+                        Item::Resource(resource) => {
+                            field_symbol(text, &resource.field()?, SymbolKind::VARIABLE)
+                        }
+                        Item::System(system) => Some(symbol(
+                            text,
+                            system.ident().unwrap_or_else(|| "sys".to_string()),
+                            SymbolKind::FUNCTION,
+                            &syntax,
+                            first_token(&syntax, Token::IDENT),
+                            queries(text, &syntax),
+                        )),
+                        Item::Test(test) => Some(symbol(
+                            text,
+                            test.name()?,
+                            SymbolKind::METHOD,
+                            &syntax,
+                            first_token(&syntax, Token::STRING),
+                            queries(text, &syntax),
+                        )),
+                        Item::Import(import) => Some(symbol(
+                            text,
+                            import.path()?,
+                            SymbolKind::MODULE,
+                            &syntax,
+                            first_token(&syntax, Token::IDENT),
+                            Vec::new(),
+                        )),
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Multi-line blocks, struct types and struct literals, and runs of comments.
+    pub fn folding_ranges(&self, url: &Url) -> Option<Vec<FoldingRange>> {
+        let (syntax, text) = self.document_syntax(url)?;
+        let line = |offset| position(text, offset).line;
+
+        let mut ranges: Vec<_> = syntax
+            .descendants()
+            .filter(|node| {
+                matches!(
+                    node.kind(),
+                    Token::Block | Token::StructType | Token::Struct
+                )
+            })
+            .map(|node| {
+                let range: Range<usize> = node.text_range().into();
+                (line(range.start), line(range.end), None)
+            })
+            .collect();
+
+        let mut comments = syntax
+            .descendants_with_tokens()
+            .filter_map(|element| element.into_token())
+            .filter(|token| token.kind() == Token::COMMENT)
+            .map(|token| line(token.text_range().start().into()))
+            .peekable();
+        while let Some(start) = comments.next() {
+            let mut end = start;
+            while comments.next_if_eq(&(end + 1)).is_some() {
+                end += 1;
+            }
+            ranges.push((start, end, Some(FoldingRangeKind::Comment)));
+        }
+
+        Some(
+            ranges
+                .into_iter()
+                .filter(|(start, end, _)| start < end)
+                .map(|(start_line, end_line, kind)| FoldingRange {
+                    start_line,
+                    end_line,
+                    kind,
+                    ..Default::default()
+                })
+                .collect(),
+        )
+    }
+
+    /// From the token at each position out through its ancestors.
+    pub fn selection_ranges(
+        &self,
+        url: &Url,
+        positions: Vec<Position>,
+    ) -> Option<Vec<SelectionRange>> {
+        let (syntax, text) = self.document_syntax(url)?;
+        positions
+            .into_iter()
+            .map(|position| {
+                let offset = offset(text, position).try_into().ok()?;
+                let token = syntax.token_at_offset(offset).right_biased()?;
+                let mut ranges: Vec<TextRange> = std::iter::once(token.text_range())
+                    .chain(token.parent_ancestors().map(|node| trimmed_range(&node)))
+                    .collect();
+                ranges.dedup();
+
+                ranges.into_iter().rev().fold(None, |parent, range| {
+                    Some(SelectionRange {
+                        range: lsp_range(text, range),
+                        parent: parent.map(Box::new),
+                    })
+                })
+            })
+            .collect()
+    }
+
+    fn document_syntax(&self, url: &Url) -> Option<(SyntaxNode, &str)> {
+        let module_id = self.module_id(url)?;
+        Some((
+            self.syntax(module_id)?,
+            self.index.modules.get_ref(module_id).source.text(),
+        ))
+    }
+}
+
+fn field_symbol(text: &str, field: &FieldType, kind: SymbolKind) -> Option<DocumentSymbol> {
+    let syntax = field.syntax();
+    let (detail, children) = match field.r#type() {
+        Some(Type::IdentifierType(r#type)) => (r#type.ident(), Vec::new()),
+        Some(Type::StructType(r#type)) => (
+            None,
+            r#type
+                .fields()
+                .filter_map(|field| field_symbol(text, &field, SymbolKind::FIELD))
+                .collect(),
+        ),
+        None => (None, Vec::new()),
+    };
+    let mut symbol = symbol(
+        text,
+        field.ident()?,
+        kind,
+        syntax,
+        first_token(syntax, Token::IDENT),
+        children,
+    );
+    // Components and resources span their keyword too
+    if let Some(parent) = syntax
+        .parent()
+        .filter(|parent| matches!(parent.kind(), Token::Component | Token::Resource))
+    {
+        symbol.range = lsp_range(text, parent.text_range());
+    }
+    symbol.detail = detail;
+    Some(symbol)
+}
+
+/// The queries in `node`, nested like in the source.
+fn queries(text: &str, node: &SyntaxNode) -> Vec<DocumentSymbol> {
+    node.children()
+        .flat_map(|child| match ast::Query::cast(child.clone()) {
+            Some(query) => vec![symbol(
+                text,
+                format!("query {}", query.entity().unwrap_or_default()),
+                SymbolKind::OBJECT,
+                &child,
+                first_token(&child, Token::IDENT),
+                queries(text, &child),
+            )],
+            None => queries(text, &child),
+        })
+        .collect()
+}
+
+fn symbol(
+    text: &str,
+    name: String,
+    kind: SymbolKind,
+    node: &SyntaxNode,
+    name_token: Option<SyntaxToken>,
+    children: Vec<DocumentSymbol>,
+) -> DocumentSymbol {
+    let range = lsp_range(text, node.text_range());
+    #[expect(deprecated)]
+    DocumentSymbol {
+        name,
+        detail: None,
+        kind,
+        tags: None,
+        deprecated: None,
+        range,
+        selection_range: name_token.map_or(range, |token| lsp_range(text, token.text_range())),
+        children: (!children.is_empty()).then_some(children),
+    }
+}
+
+/// The range of `node` without the whitespace and comments at its edges.
+fn trimmed_range(node: &SyntaxNode) -> TextRange {
+    let mut tokens = node
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| {
+            !matches!(
+                token.kind(),
+                Token::WHITE_SPACE | Token::NEW_LINE | Token::COMMENT
+            )
+        });
+    let Some(first) = tokens.next() else {
+        return node.text_range();
+    };
+    let last = tokens.last().unwrap_or_else(|| first.clone());
+    TextRange::new(first.text_range().start(), last.text_range().end())
+}
+
+fn first_token(node: &SyntaxNode, kind: Token) -> Option<SyntaxToken> {
+    node.children_with_tokens()
+        .filter_map(|element| element.into_token())
+        .find(|token| token.kind() == kind)
+}
+
+fn lsp_range(text: &str, range: TextRange) -> lsp_types::Range {
+    lsp_types::Range {
+        start: position(text, range.start().into()),
+        end: position(text, range.end().into()),
+    }
+}
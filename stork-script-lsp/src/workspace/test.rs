@@ -0,0 +1,1043 @@
+use std::{collections::HashMap, fs};
+
+use async_lsp::lsp_types::{
+    CodeActionOrCommand, DiagnosticSeverity, DocumentSymbol, Hover, HoverContents, InlayHintLabel,
+    Location, Position, Range, TextDocumentContentChangeEvent, TextEdit, Url,
+};
+use bevy_reflect::TypeRegistry;
+use stork_script_bevy::stork_std;
+use stork_script_core::{
+    hir::BuiltinInfo,
+    passes::type_resolution::InnerResolvedType,
+    schema::{Builtin, Schema, SCHEMA_VERSION},
+};
+
+use super::{offset, position, semantic_tokens::TokenType, Workspace};
+
+fn url(name: &str) -> Url {
+    Url::parse(&format!("untitled:{name}.strk")).unwrap()
+}
+
+#[test]
+fn positions() {
+    let text = "# héllo 🦀\nsys x {}";
+    assert_eq!(position(text, 0), Position::new(0, 0));
+    let crab = text.find('🦀').unwrap();
+    assert_eq!(position(text, crab), Position::new(0, 8));
+    assert_eq!(position(text, crab + 4), Position::new(0, 10));
+    assert_eq!(
+        position(text, text.find("x {").unwrap()),
+        Position::new(1, 4)
+    );
+
+    for offset_ in [0, crab, crab + 4, text.len()] {
+        assert_eq!(offset(text, position(text, offset_)), offset_);
+    }
+    // Past the end of a line
+    assert_eq!(offset(text, Position::new(0, 100)), crab + 4);
+}
+
+#[test]
+fn diagnostics() {
+    let mut workspace = Workspace::default();
+    let main = url("main");
+    workspace.open(
+        main.clone(),
+        "# é🦀\nsys {\n    let a = b;\n}\n".to_string(),
+    );
+
+    let diagnostics = workspace.diagnostics(&main);
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0].message, "Couldn't find name");
+    assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+    assert_eq!(
+        diagnostics[0].range,
+        Range::new(Position::new(2, 12), Position::new(2, 13))
+    );
+    assert_eq!(diagnostics[1].severity, Some(DiagnosticSeverity::WARNING));
+
+    workspace.change(
+        &main,
+        vec![TextDocumentContentChangeEvent {
+            range: Some(Range::new(Position::new(2, 12), Position::new(2, 13))),
+            range_length: None,
+            text: "1".to_string(),
+        }],
+    );
+    let module_id = workspace.module_id(&main).unwrap();
+    assert_eq!(
+        workspace.index.modules.get_ref(module_id).source.text(),
+        "# é🦀\nsys {\n    let a = 1;\n}\n"
+    );
+    let diagnostics = workspace.diagnostics(&main);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "Unused variable `a`\n`#[allow(unused_variables)]` above the item silences this\nif this is intended, call it `_a`");
+
+    workspace.change(
+        &main,
+        vec![TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: "sys { ".to_string(),
+        }],
+    );
+    assert!(!workspace.diagnostics(&main).is_empty());
+
+    workspace.close(&main);
+    assert!(workspace.diagnostics(&main).is_empty());
+}
+
+#[test]
+fn files_next_to_documents() {
+    let dir = std::env::temp_dir().join(format!("stork-lsp-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("physics.strk"), "pub comp Velocity: f32").unwrap();
+    fs::write(dir.join("main.strk"), "").unwrap();
+
+    let mut workspace = Workspace::default();
+    let main = Url::from_file_path(dir.join("main.strk")).unwrap();
+    workspace.open(
+        main.clone(),
+        "use physics\nsys { query e { e[Velocity] = 1; } }".to_string(),
+    );
+    assert!(workspace.diagnostics(&main).is_empty());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn module_paths() {
+    let dir = std::env::temp_dir().join(format!("stork-lsp-paths-{}", std::process::id()));
+    fs::create_dir_all(dir.join("game")).unwrap();
+    fs::write(dir.join("game/physics.strk"), "pub comp Velocity: f32").unwrap();
+    fs::write(dir.join("physics.strk"), "").unwrap();
+
+    let mut workspace = Workspace::default();
+    workspace.set_root(dir.clone());
+    let main = Url::from_file_path(dir.join("main.strk")).unwrap();
+    workspace.open(
+        main.clone(),
+        "use game::physics\nsys { query e { e[Velocity] = 1; } }".to_string(),
+    );
+    assert!(workspace.diagnostics(&main).is_empty());
+
+    // Documents outside the root are named after their stem
+    let other = url("physics");
+    workspace.open(other.clone(), String::new());
+    let diagnostics = workspace.diagnostics(&other);
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0]
+        .message
+        .starts_with("`physics` is already the module in"));
+
+    let std = url("std");
+    workspace.open(std.clone(), String::new());
+    assert_eq!(
+        workspace.diagnostics(&std)[0].message,
+        "`std` is builtin, rename this file"
+    );
+    assert!(workspace.diagnostics(&main).is_empty());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+fn open(workspace: &mut Workspace, name: &str, text: &str) -> Url {
+    let url = url(name);
+    workspace.open(url.clone(), text.to_string());
+    url
+}
+
+fn at(url: &Url, line: u32, character: u32) -> Location {
+    Location::new(
+        url.clone(),
+        Range::new(
+            Position::new(line, character),
+            Position::new(line, character + 1),
+        ),
+    )
+}
+
+#[test]
+fn definitions_and_references() {
+    let mut workspace = Workspace::default();
+    let physics = open(
+        &mut workspace,
+        "physics",
+        "pub comp V: f32\npub res G: f32\n",
+    );
+    let main = open(
+        &mut workspace,
+        "main",
+        "use physics\nuse std\nsys s {\n    let a = [G];\n    query e {\n        e[V] = a + a;\n        print(e[V])\n    }\n}\n",
+    );
+    assert!(workspace.diagnostics(&main).is_empty());
+
+    // Variables, queries, components and resources
+    assert_eq!(
+        workspace.definition(&main, Position::new(5, 16)),
+        Some(at(&main, 3, 8))
+    );
+    assert_eq!(
+        workspace.definition(&main, Position::new(5, 8)),
+        Some(at(&main, 4, 10))
+    );
+    assert_eq!(
+        workspace.definition(&main, Position::new(5, 10)),
+        Some(at(&physics, 0, 9))
+    );
+    assert_eq!(
+        workspace.definition(&main, Position::new(3, 13)),
+        Some(at(&physics, 1, 8))
+    );
+    // Modules
+    assert_eq!(
+        workspace.definition(&main, Position::new(0, 6)),
+        Some(Location::new(physics.clone(), Range::default()))
+    );
+    // `std` has no source
+    assert_eq!(workspace.definition(&main, Position::new(6, 9)), None);
+    assert_eq!(workspace.definition(&main, Position::new(1, 5)), None);
+
+    assert_eq!(
+        workspace.references(&main, Position::new(3, 8), false),
+        Some(vec![at(&main, 5, 15), at(&main, 5, 19)])
+    );
+    assert_eq!(
+        workspace.references(&physics, Position::new(0, 9), true),
+        Some(vec![at(&main, 5, 10), at(&main, 6, 16), at(&physics, 0, 9)])
+    );
+    assert_eq!(
+        workspace.references(&main, Position::new(0, 6), false),
+        Some(vec![Location::new(
+            main.clone(),
+            Range::new(Position::new(0, 4), Position::new(0, 11))
+        )])
+    );
+}
+
+fn hover(workspace: &Workspace, url: &Url, line: u32, character: u32) -> String {
+    match workspace.hover(url, Position::new(line, character)) {
+        Some(Hover {
+            contents: HoverContents::Markup(markup),
+            ..
+        }) => markup.value,
+        hover => panic!("Unexpected hover {hover:?}"),
+    }
+}
+
+#[test]
+fn hovers() {
+    let mut workspace = Workspace::default();
+    let main = open(
+        &mut workspace,
+        "main",
+        "use std\ncomp V: { x: f32, y: f32 }\nres G: f32\nsys fall {\n    let a = 2;\n    query e {\n        e[V].y = e[V].x - a * [G];\n    }\n}\n",
+    );
+    assert!(workspace.diagnostics(&main).is_empty());
+
+    assert_eq!(
+        hover(&workspace, &main, 6, 10),
+        "```stork\nV { x: f32, y: f32 }\n```\n\nFrom the ECS"
+    );
+    assert_eq!(hover(&workspace, &main, 4, 8), "```stork\nf32\n```");
+    assert_eq!(
+        hover(&workspace, &main, 3, 1),
+        "```stork\nsys fall\n```\n\nReads: `G` (resource), `V` of `e`\n\nWrites: `V` of `e`"
+    );
+    assert_eq!(
+        hover(&workspace, &main, 5, 6),
+        "```stork\nquery e\n```\n\nReads: `G` (resource), `V` of `e`\n\nWrites: `V` of `e`"
+    );
+}
+
+/// Completions at the `$` in `text`, as `label: detail`.
+fn completions(text: &str) -> Vec<String> {
+    let mut workspace = Workspace::default();
+    let cursor = text.find('$').unwrap();
+    let text = text.replace('$', "");
+    let main = open(&mut workspace, "main", &text);
+    workspace
+        .completions(&main, position(&text, cursor))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|item| match item.detail {
+            Some(detail) => format!("{}: {detail}", item.label),
+            None => item.label,
+        })
+        .collect()
+}
+
+#[test]
+fn completion() {
+    let prelude = "use std\ncomp V: { x: f32, y: f32 }\nres G: f32\n";
+    assert_eq!(
+        completions(&format!(
+            "{prelude}sys {{\n    query e {{\n        let b = e[V].$\n    }}\n}}\n"
+        )),
+        ["x: f32", "y: f32"]
+    );
+    assert_eq!(
+        completions(&format!(
+            "{prelude}sys {{\n    query e {{\n        let b = e[$\n    }}\n}}\n"
+        )),
+        ["V: { x: f32, y: f32 }"]
+    );
+    assert_eq!(
+        completions(&format!("{prelude}sys {{\n    let b = [G$\n}}\n")),
+        ["G: f32"]
+    );
+
+    let names = completions(&format!(
+        "{prelude}sys fall {{\n    let a = 2;\n    query e {{\n        $\n        let b = 1;\n    }}\n    let c = 1;\n}}\n"
+    ));
+    assert_eq!(
+        names,
+        [
+            "a: f32",
+            "assert: fn(bool, String) -> ()",
+            "e: Entity",
+            "fall: fn() -> ()",
+            "print: fn(f32) -> ()",
+            "spawn: fn() -> Entity"
+        ]
+    );
+}
+
+#[test]
+fn renames() {
+    let mut workspace = Workspace::default();
+    let physics = open(&mut workspace, "physics", "pub comp V: { x: f32 }\n");
+    let main = open(
+        &mut workspace,
+        "main",
+        "use physics\nuse std\nres G: f32\nsys {\n    let a = 1;\n    query e {\n        e[V] = V { x: a };\n    }\n    print(a)\n}\n",
+    );
+    let diagnostics = workspace.diagnostics(&main);
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0]
+        .message
+        .starts_with("Resource `G` is never accessed"));
+
+    assert_eq!(
+        workspace.prepare_rename(&main, Position::new(6, 11)),
+        Ok(Some(Range::new(Position::new(6, 10), Position::new(6, 11))))
+    );
+    let edit = workspace
+        .rename(&main, Position::new(6, 11), "Velocity")
+        .unwrap()
+        .unwrap();
+    let mut changes = edit.changes.unwrap();
+    let edits = |changes: &mut HashMap<Url, Vec<TextEdit>>, url: &Url| {
+        let mut edits = changes.remove(url).unwrap_or_default();
+        edits.sort_by_key(|edit| edit.range.start);
+        edits
+            .into_iter()
+            .map(|edit| (edit.range.start, edit.new_text))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        edits(&mut changes, &physics),
+        [(Position::new(0, 9), "Velocity".to_string())]
+    );
+    assert_eq!(
+        edits(&mut changes, &main),
+        [
+            (Position::new(6, 10), "Velocity".to_string()),
+            (Position::new(6, 15), "Velocity".to_string())
+        ]
+    );
+
+    // Builtins, collisions and invalid names
+    assert!(workspace
+        .prepare_rename(&main, Position::new(1, 5))
+        .is_err());
+    assert!(workspace.rename(&main, Position::new(6, 10), "G").is_err());
+    assert!(workspace.rename(&main, Position::new(4, 8), "e").is_err());
+    assert!(workspace
+        .rename(&main, Position::new(4, 8), "print")
+        .is_err());
+    assert!(workspace
+        .rename(&main, Position::new(4, 8), "query")
+        .is_err());
+    assert!(workspace.rename(&main, Position::new(4, 8), "b").is_ok());
+}
+
+#[test]
+fn renames_imported_names() {
+    let mut workspace = Workspace::default();
+    let physics = open(&mut workspace, "physics", "pub comp Velocity: f32\n");
+    let main = open(
+        &mut workspace,
+        "main",
+        "use physics::{Velocity}\nsys { query e { e[Velocity] = 1; } }\n",
+    );
+    let aliased = open(
+        &mut workspace,
+        "aliased",
+        "use physics::{Velocity as V}\nsys { query e { e[V] = 1; } }\n",
+    );
+    assert!(workspace.diagnostics(&main).is_empty());
+    assert!(workspace.diagnostics(&aliased).is_empty());
+
+    let edit = workspace
+        .rename(&physics, Position::new(0, 9), "Speed")
+        .unwrap()
+        .unwrap();
+    let mut changes = edit.changes.unwrap();
+    let mut edits = |url: &Url| {
+        let mut edits = changes.remove(url).unwrap_or_default();
+        edits.sort_by_key(|edit| edit.range.start);
+        edits
+            .into_iter()
+            .map(|edit| (edit.range.start, edit.new_text))
+            .collect::<Vec<_>>()
+    };
+    let speed = |line, character| (Position::new(line, character), "Speed".to_string());
+    assert_eq!(edits(&physics), [speed(0, 9)]);
+    assert_eq!(edits(&main), [speed(0, 14), speed(1, 18)]);
+    // The alias stays, only the name it's for changes
+    assert_eq!(edits(&aliased), [speed(0, 14)]);
+}
+
+#[test]
+fn semantic_tokens() {
+    let mut workspace = Workspace::default();
+    let main = open(
+        &mut workspace,
+        "main",
+        "use std\ncomp V: f32\nres G: f32\nsys s {\n    let a = [G];\n    query e {\n        e[V] = a;\n        print(e[V]);\n    }\n}\n",
+    );
+    assert!(workspace.diagnostics(&main).is_empty());
+
+    let decode = |range| {
+        let mut position = Position::default();
+        workspace
+            .semantic_tokens(&main, range)
+            .unwrap()
+            .data
+            .into_iter()
+            .map(|token| {
+                if token.delta_line > 0 {
+                    position.character = 0;
+                }
+                position.line += token.delta_line;
+                position.character += token.delta_start;
+                let token_type = TokenType::ALL[token.token_type as usize];
+                (
+                    position.line,
+                    position.character,
+                    token.length,
+                    token_type,
+                    token.token_modifiers_bitset,
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    use TokenType::*;
+    assert_eq!(
+        decode(None),
+        [
+            (1, 5, 1, Component, 1),
+            (1, 8, 3, Type, 2),
+            (2, 4, 1, Resource, 1),
+            (2, 7, 3, Type, 2),
+            (3, 4, 1, System, 1),
+            (4, 8, 1, Variable, 1),
+            (4, 13, 1, Resource, 0),
+            (5, 10, 1, Entity, 1),
+            (6, 8, 1, Entity, 0),
+            (6, 10, 1, Component, 0),
+            (6, 15, 1, Variable, 0),
+            (7, 8, 5, Function, 2),
+            (7, 14, 1, Entity, 0),
+            (7, 16, 1, Component, 0),
+        ]
+    );
+    assert_eq!(
+        decode(Some(Range::new(Position::new(6, 0), Position::new(6, 12)))),
+        [(6, 8, 1, Entity, 0), (6, 10, 1, Component, 0)]
+    );
+}
+
+#[test]
+fn structure() {
+    let mut workspace = Workspace::default();
+    let main = open(
+        &mut workspace,
+        "main",
+        "use std\n# A\n# B\ncomp V: {\n    x: f32,\n}\nres G: f32\nsys s {\n    query e {\n        e[V].x = [G];\n    }\n}\n",
+    );
+
+    fn outline(symbols: Vec<DocumentSymbol>) -> Vec<String> {
+        symbols
+            .into_iter()
+            .map(|symbol| {
+                let children = outline(symbol.children.unwrap_or_default());
+                format!(
+                    "{:?} {}@{}{}",
+                    symbol.kind,
+                    symbol.name,
+                    symbol.selection_range.start.line,
+                    if children.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" [{}]", children.join(", "))
+                    }
+                )
+            })
+            .collect()
+    }
+    assert_eq!(
+        outline(workspace.document_symbols(&main).unwrap()),
+        [
+            "Module std@0",
+            "Struct V@3 [Field x@4]",
+            "Variable G@6",
+            "Function s@7 [Object query e@8]"
+        ]
+    );
+
+    let folds = workspace
+        .folding_ranges(&main)
+        .unwrap()
+        .into_iter()
+        .map(|range| (range.start_line, range.end_line))
+        .collect::<Vec<_>>();
+    assert_eq!(folds, [(3, 5), (7, 11), (8, 10), (1, 2)]);
+
+    let selection = workspace
+        .selection_ranges(&main, vec![Position::new(9, 13)])
+        .unwrap()
+        .remove(0);
+    let mut ranges = vec![selection.range];
+    let mut parent = selection.parent;
+    while let Some(range) = parent {
+        ranges.push(range.range);
+        parent = range.parent;
+    }
+    assert_eq!(
+        ranges[..3],
+        [
+            Range::new(Position::new(9, 13), Position::new(9, 14)),
+            Range::new(Position::new(9, 8), Position::new(9, 14)),
+            Range::new(Position::new(9, 8), Position::new(9, 20)),
+        ]
+    );
+    assert_eq!(ranges.last().unwrap().start, Position::new(0, 0));
+}
+
+#[test]
+fn basic_std() {
+    // What `stork schema` exports for a game without types of its own
+    let mut type_registry = TypeRegistry::new();
+    type_registry.register::<()>();
+    assert_eq!(
+        Schema::from_module(&stork_std::new_module(&type_registry)),
+        super::basic_std()
+    );
+}
+
+#[test]
+fn schema() {
+    let text = "use std\nsys { query e { e[Health] = 1; } }";
+    let mut workspace = Workspace::default();
+    let main = open(&mut workspace, "main", text);
+    assert!(!workspace.diagnostics(&main).is_empty());
+
+    let mut workspace = Workspace::new(Some(Schema {
+        version: SCHEMA_VERSION,
+        builtins: vec![
+            Builtin {
+                identifier: "Health".into(),
+                r#type: InnerResolvedType::F32,
+                info: BuiltinInfo {
+                    component: true,
+                    ..Default::default()
+                },
+            },
+            Builtin {
+                identifier: "Score".into(),
+                r#type: InnerResolvedType::F32,
+                info: BuiltinInfo {
+                    resource: true,
+                    ..Default::default()
+                },
+            },
+            Builtin {
+                identifier: "heal".into(),
+                r#type: InnerResolvedType::Function {
+                    params: vec![InnerResolvedType::F32],
+                    ret: Box::new(InnerResolvedType::Unit),
+                },
+                info: BuiltinInfo {
+                    param_names: vec![Some("amount".to_string())],
+                    ..Default::default()
+                },
+            },
+        ],
+    }));
+    let main = open(&mut workspace, "main", text);
+    assert!(workspace.diagnostics(&main).is_empty());
+
+    // Parameter names come from the schema too
+    let main = open(&mut workspace, "healing", "use std\nsys { heal(1); }");
+    let help = workspace
+        .signature_help(&main, Position::new(1, 11))
+        .unwrap();
+    assert_eq!(help.signatures[0].label, "heal(amount: f32) -> ()");
+
+    let main = open(
+        &mut workspace,
+        "scoring",
+        "use std\nsys { query e { e[Health] = [Score]; } }",
+    );
+    assert!(workspace.diagnostics(&main).is_empty());
+    let token_types = workspace
+        .semantic_tokens(&main, None)
+        .unwrap()
+        .data
+        .into_iter()
+        .map(|token| TokenType::ALL[token.token_type as usize])
+        .filter(|token_type| matches!(token_type, TokenType::Component | TokenType::Resource))
+        .collect::<Vec<_>>();
+    assert_eq!(token_types, [TokenType::Component, TokenType::Resource]);
+}
+
+#[test]
+fn inlay_hints() {
+    let mut workspace = Workspace::default();
+    let main = open(
+        &mut workspace,
+        "main",
+        "use std\ntest \"hints\" {\n    let a = 2;\n    query e {\n        assert(a == 2, \"two\");\n    }\n}\n",
+    );
+    assert!(workspace.diagnostics(&main).is_empty());
+
+    let hints = |range| {
+        workspace
+            .inlay_hints(&main, range)
+            .unwrap()
+            .into_iter()
+            .map(|hint| match hint.label {
+                InlayHintLabel::String(label) => {
+                    (hint.position.line, hint.position.character, label)
+                }
+                label => panic!("Unexpected label {label:?}"),
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        hints(Range::new(Position::new(0, 0), Position::new(7, 0))),
+        [
+            (2, 9, ": f32".to_string()),
+            (3, 11, ": Entity".to_string()),
+            (4, 15, "condition:".to_string()),
+            (4, 23, "message:".to_string()),
+        ]
+    );
+    assert_eq!(
+        hints(Range::new(Position::new(3, 0), Position::new(3, 20))),
+        [(3, 11, ": Entity".to_string())]
+    );
+}
+
+#[test]
+fn signature_help() {
+    let mut workspace = Workspace::default();
+    let main = open(
+        &mut workspace,
+        "main",
+        "use std\ntest \"f\" {\n    assert(1 < 2, \"x\");\n    tick();\n}\nsys tick {}\n",
+    );
+    assert!(workspace.diagnostics(&main).is_empty());
+
+    let help = |line, character| {
+        let help = workspace.signature_help(&main, Position::new(line, character))?;
+        let signature = help.signatures.into_iter().next().unwrap();
+        Some((signature.label, help.active_parameter))
+    };
+    let assert = "assert(condition: bool, message: String) -> ()".to_string();
+    assert_eq!(help(2, 11), Some((assert.clone(), Some(0))));
+    assert_eq!(help(2, 17), Some((assert, Some(1))));
+    assert_eq!(
+        help(2, 13),
+        Some(("<(f32, f32) -> bool".to_string(), Some(0)))
+    );
+    assert_eq!(
+        help(2, 15),
+        Some(("<(f32, f32) -> bool".to_string(), Some(1)))
+    );
+    assert_eq!(help(3, 9), Some(("tick() -> ()".to_string(), None)));
+    assert_eq!(help(2, 3), None);
+    assert_eq!(help(3, 11), None);
+}
+
+#[test]
+fn code_actions() {
+    let mut workspace = Workspace::default();
+    let physics = open(&mut workspace, "physics", "pub comp Velocity: { x: f32 }\n");
+    let main = open(
+        &mut workspace,
+        "main",
+        "use std\nuse physics\nsys {\n    query e {\n        e[Velocity] = Velocity { X: 1 };\n    }\n    print();\n}\n",
+    );
+    let other = open(
+        &mut workspace,
+        "other",
+        "use std\nsys {\n    query e {\n        e[Velocity] = 1;\n    }\n}\n",
+    );
+
+    let actions = |url: &Url, line| {
+        workspace
+            .code_actions(
+                url,
+                Range::new(Position::new(line, 0), Position::new(line, 40)),
+            )
+            .unwrap()
+            .into_iter()
+            .map(|action| {
+                let CodeActionOrCommand::CodeAction(action) = action else {
+                    panic!("Unexpected command {action:?}");
+                };
+                let edits = action
+                    .edit
+                    .and_then(|edit| edit.changes)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .flat_map(|(url, edits)| {
+                        edits
+                            .into_iter()
+                            .map(move |edit| (url.clone(), edit.range, edit.new_text))
+                    })
+                    .collect::<Vec<_>>();
+                (action.title, edits)
+            })
+            .collect::<Vec<_>>()
+    };
+    let range = |line, start, end| Range::new(Position::new(line, start), Position::new(line, end));
+
+    assert_eq!(
+        actions(&main, 4),
+        [(
+            "Replace with `x`".to_string(),
+            vec![(main.clone(), range(4, 33, 34), "x".to_string())]
+        )]
+    );
+    assert_eq!(
+        actions(&main, 6),
+        [(
+            "Add the missing arguments".to_string(),
+            vec![(main.clone(), range(6, 10, 10), "0".to_string())]
+        )]
+    );
+    assert_eq!(
+        actions(&other, 3),
+        [(
+            "Import `physics`".to_string(),
+            vec![(other.clone(), range(0, 0, 0), "use physics\n".to_string())]
+        )]
+    );
+    assert!(actions(&physics, 0).is_empty());
+}
+
+#[test]
+fn lints() {
+    let mut workspace = Workspace::default();
+    open(&mut workspace, "physics", "comp V: f32\n");
+    let main = open(
+        &mut workspace,
+        "main",
+        "use std\nuse physics\ncomp Unused: f32\n#[allow(dead_code)]\ncomp Allowed: f32\nsys {\n    let a = 1;\n    let _b = 2;\n    let print = 3;\n}\n#[allow(unused_variables, shadowed_builtins)]\nsys quiet {\n    let spawn = 1;\n}\n",
+    );
+
+    let mut diagnostics = workspace
+        .diagnostics(&main)
+        .into_iter()
+        .map(|diagnostic| {
+            assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::WARNING));
+            let message = diagnostic.message.lines().next().unwrap().to_string();
+            (diagnostic.range.start.line, message)
+        })
+        .collect::<Vec<_>>();
+    diagnostics.sort();
+    assert_eq!(
+        diagnostics,
+        [
+            (1, "Unused import `physics`".to_string()),
+            (2, "Component `Unused` is never accessed".to_string()),
+            (6, "Unused variable `a`".to_string()),
+            (8, "Unused variable `print`".to_string()),
+            (8, "`print` shadows a builtin from `std`".to_string()),
+        ]
+    );
+
+    let actions = workspace
+        .code_actions(&main, Range::new(Position::new(1, 0), Position::new(1, 0)))
+        .unwrap();
+    assert_eq!(actions.len(), 1);
+    let CodeActionOrCommand::CodeAction(action) = &actions[0] else {
+        panic!("Unexpected command {:?}", actions[0]);
+    };
+    assert_eq!(action.title, "Remove `use physics`");
+}
+
+#[test]
+fn suggestions() {
+    let mut workspace = Workspace::default();
+    open(
+        &mut workspace,
+        "physics",
+        "pub comp Velocity: { x: f32, y: f32 }\n",
+    );
+    open(&mut workspace, "health", "pub res Health: f32\n");
+    let main = open(
+        &mut workspace,
+        "main",
+        "use std\nuse physics\nsys {\n    prnt(1);\n    query e {\n        e[Velocty].y = 1;\n        e[Velocity].Y = 1;\n        let _h = Healt;\n    }\n}\n",
+    );
+
+    let mut diagnostics = workspace
+        .diagnostics(&main)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.range.start.line, diagnostic.message))
+        .collect::<Vec<_>>();
+    diagnostics.sort();
+    assert_eq!(
+        diagnostics,
+        [
+            (3, "Couldn't find name\ndid you mean `print`?".to_string()),
+            (
+                5,
+                "Couldn't find name\ndid you mean `Velocity`?".to_string()
+            ),
+            (6, "Unknown field `Y`\ndid you mean `y`?".to_string()),
+            (
+                7,
+                "Couldn't find name\ndid you mean `Health` from `health`?".to_string()
+            ),
+        ]
+    );
+
+    let actions = workspace
+        .code_actions(&main, Range::new(Position::new(3, 0), Position::new(3, 20)))
+        .unwrap();
+    let [CodeActionOrCommand::CodeAction(action)] = &actions[..] else {
+        panic!("Unexpected actions {actions:?}");
+    };
+    assert_eq!(action.title, "Replace with `print`");
+}
+
+#[test]
+fn duplicates() {
+    let mut workspace = Workspace::default();
+    let physics = open(&mut workspace, "physics", "pub comp Velocity: f32\n");
+    let motion = open(
+        &mut workspace,
+        "motion",
+        "pub comp Velocity: f32\npub res Speed: f32\n",
+    );
+    let main = open(
+        &mut workspace,
+        "main",
+        "use physics\nuse motion\ncomp Speed: f32\ncomp Speed: f32\nsys {\n    query e {\n        e[Velocity] = 1;\n        e[Speed] = 2;\n    }\n}\n",
+    );
+
+    let errors = workspace
+        .diagnostics(&main)
+        .into_iter()
+        .filter(|diagnostic| diagnostic.severity == Some(DiagnosticSeverity::ERROR))
+        .collect::<Vec<_>>();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(
+        errors[0].message,
+        "`Speed` is defined multiple times\nrename one of them"
+    );
+    assert_eq!(errors[0].range.start.line, 3);
+    // Where each label starts
+    let related = |index: usize| {
+        errors[index]
+            .related_information
+            .iter()
+            .flatten()
+            .map(|related| {
+                let start = related.location.range.start;
+                (
+                    related.location.uri.clone(),
+                    start.line,
+                    related.message.clone(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        related(0)[1],
+        (main.clone(), 2, "first defined here".to_string())
+    );
+
+    assert_eq!(
+        errors[1].message,
+        "`Velocity` is ambiguous\nit's declared in `physics`, `motion`\ndeclare it in this module to shadow them, or import only one"
+    );
+    assert_eq!(
+        related(1)[1..],
+        [
+            (physics, 0, "declared here".to_string()),
+            (motion, 0, "declared here".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn imports() {
+    let mut workspace = Workspace::default();
+    let physics = open(&mut workspace, "physics", "use main\npub comp V: f32\n");
+    let main = open(
+        &mut workspace,
+        "main",
+        "use sdt\nuse physics\nsys {\n    query e {\n        e[V] = 1;\n    }\n}\n",
+    );
+
+    let errors = |url| {
+        workspace
+            .diagnostics(url)
+            .into_iter()
+            .filter(|diagnostic| diagnostic.severity == Some(DiagnosticSeverity::ERROR))
+            .map(|diagnostic| (diagnostic.range.start.line, diagnostic.message))
+            .collect::<Vec<_>>()
+    };
+    // `V` still resolves despite the errors
+    assert_eq!(
+        errors(&main),
+        [
+            (
+                0,
+                "Couldn't find module `sdt`\ndid you mean `std`?".to_string()
+            ),
+            (
+                1,
+                "Import cycle\nthe modules import each other: `main` -> `physics` -> `main`"
+                    .to_string()
+            ),
+        ]
+    );
+    assert_eq!(
+        errors(&physics),
+        [(
+            0,
+            "Import cycle\nthe modules import each other: `physics` -> `main` -> `physics`"
+                .to_string()
+        )]
+    );
+
+    let actions = workspace
+        .code_actions(&main, Range::new(Position::new(0, 0), Position::new(0, 7)))
+        .unwrap();
+    let [CodeActionOrCommand::CodeAction(action)] = &actions[..] else {
+        panic!("Unexpected actions {actions:?}");
+    };
+    assert_eq!(action.title, "Replace with `std`");
+}
+
+#[test]
+fn qualified_imports() {
+    let mut workspace = Workspace::default();
+    open(&mut workspace, "physics", "pub comp Velocity: { x: f32 }\n");
+    let main = open(
+        &mut workspace,
+        "main",
+        "use physics as phys\nuse std::{print as p, prnt}\nsys {\n    query e {\n        e[phys::Velocity].x = 1;\n        p(e[physics::Velocity].x);\n        e[phys::Velocty].x = 2;\n        e[phy::Velocity].x = 3;\n    }\n}\n",
+    );
+
+    let errors = workspace
+        .diagnostics(&main)
+        .into_iter()
+        .filter(|diagnostic| diagnostic.severity == Some(DiagnosticSeverity::ERROR))
+        .map(|diagnostic| (diagnostic.range.start.line, diagnostic.message))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            (
+                1,
+                "Couldn't find `prnt` in `std`\ndid you mean `print`?".to_string()
+            ),
+            (
+                6,
+                "Couldn't find `Velocty` in `physics`\ndid you mean `Velocity`?".to_string()
+            ),
+            (
+                7,
+                "Couldn't find module `phy`\ndid you mean `phys`?".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn visibility() {
+    let mut workspace = Workspace::default();
+    let physics = open(
+        &mut workspace,
+        "physics",
+        "pub comp Velocity: f32\ncomp Mass: f32\n",
+    );
+    let main = open(
+        &mut workspace,
+        "main",
+        "use physics\nsys {\n    query e {\n        e[Velocity] = 1;\n        e[Mass] = 2;\n        e[physics::Mass] = 3;\n    }\n}\n",
+    );
+
+    let private = "`Mass` is private\nit can only be used in `physics`\ndeclare it with `pub`";
+    let errors = workspace
+        .diagnostics(&main)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.range.start.line, diagnostic.message))
+        .collect::<Vec<_>>();
+    assert_eq!(errors, [(4, private.to_string()), (5, private.to_string())]);
+    // `Mass` is still used, and `pub` items are never reported as unused
+    assert!(workspace.diagnostics(&physics).is_empty());
+
+    let actions = workspace
+        .code_actions(&main, Range::new(Position::new(4, 0), Position::new(4, 20)))
+        .unwrap();
+    let [CodeActionOrCommand::CodeAction(action)] = &actions[..] else {
+        panic!("Unexpected actions {actions:?}");
+    };
+    assert_eq!(action.title, "Make `Mass` public");
+    let edits = action.edit.as_ref().unwrap().changes.as_ref().unwrap();
+    assert_eq!(
+        edits[&physics],
+        [TextEdit {
+            range: Range::new(Position::new(1, 0), Position::new(1, 0)),
+            new_text: "pub ".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn test_only() {
+    let mut workspace = Workspace::default();
+    let main = open(
+        &mut workspace,
+        "main",
+        "use std\nsys tick {}\nsys {\n    let _e = spawn();\n    tick();\n}\ntest \"t\" {\n    let _e = spawn();\n    tick();\n}\n",
+    );
+
+    let note = "systems only have access to the components and resources they use";
+    let errors = workspace
+        .diagnostics(&main)
+        .into_iter()
+        .map(|diagnostic| (diagnostic.range.start.line, diagnostic.message))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            (3, format!("`spawn` can only be used in tests\n{note}")),
+            (
+                4,
+                format!("System `tick` can only be called in tests\n{note}")
+            ),
+        ]
+    );
+}
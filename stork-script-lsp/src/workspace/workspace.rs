@@ -0,0 +1,377 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use async_lsp::lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, NumberOrString,
+    Position, Range, TextDocumentContentChangeEvent, Url,
+};
+use rowan::GreenNode;
+use stork_script_core::{
+    cst::SyntaxNode,
+    module_index::{module_path, Module, ModuleID, ModuleIndex},
+    report::{Report, ReportKind, Span},
+    schema::Schema,
+};
+
+/// Open documents compiled together with the other `.strk` files under the
+/// root and `std`. Modules are named after their path from the root, like
+/// `game::physics` for `game/physics.strk`, other documents after their stem.
+pub struct Workspace {
+    /// The workspace folder, or the directory of the first opened document
+    root: Option<PathBuf>,
+    documents: HashMap<Url, String>,
+    /// Files under the root, as they were on disk when it was loaded or when
+    /// their document was closed
+    files: HashMap<Url, String>,
+    /// Why documents that aren't modules couldn't be loaded, like a duplicate
+    /// path or source that couldn't be parsed at all
+    unloaded: HashMap<Url, Diagnostic>,
+    pub index: ModuleIndex,
+    /// Where each module came from, `std` isn't in here
+    urls: HashMap<ModuleID, Url>,
+    syntax: HashMap<ModuleID, GreenNode>,
+    /// The game's `std`, otherwise one with only the basic types is used
+    schema: Schema,
+}
+
+impl Default for Workspace {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl Workspace {
+    pub fn new(schema: Option<Schema>) -> Self {
+        Self {
+            root: None,
+            documents: Default::default(),
+            files: Default::default(),
+            unloaded: Default::default(),
+            index: Default::default(),
+            urls: Default::default(),
+            syntax: Default::default(),
+            schema: schema.unwrap_or_else(basic_std),
+        }
+    }
+
+    /// Loads the `.strk` files under `root`.
+    pub fn set_root(&mut self, root: PathBuf) {
+        self.files.clear();
+        self.load_dir(&root);
+        self.root = Some(root);
+    }
+
+    pub fn open(&mut self, url: Url, text: String) {
+        if self.root.is_none() {
+            if let Some(dir) = url
+                .to_file_path()
+                .ok()
+                .and_then(|path| Some(path.parent()?.to_path_buf()))
+            {
+                self.set_root(dir);
+            }
+        }
+        self.documents.insert(url, text);
+        self.load();
+    }
+
+    pub fn change(&mut self, url: &Url, changes: Vec<TextDocumentContentChangeEvent>) {
+        let Some(text) = self.documents.get_mut(url) else {
+            return;
+        };
+        for change in changes {
+            match change.range {
+                Some(range) => {
+                    let range = offset(text, range.start)..offset(text, range.end);
+                    text.replace_range(range, &change.text);
+                }
+                None => *text = change.text,
+            }
+        }
+
+        // Only the changed document needs to be parsed again
+        let Some(module_id) = self.module_id(url) else {
+            return self.load();
+        };
+        match Module::parse(&self.documents[url], module_id) {
+            Ok((module, green)) => {
+                self.index.replace_module(module_id, module);
+                self.syntax.insert(module_id, green);
+                self.compile();
+            }
+            Err(_) => self.load(),
+        }
+    }
+
+    pub fn close(&mut self, url: &Url) {
+        self.documents.remove(url);
+        // It might have been saved, or created
+        if let Ok(path) = url.to_file_path() {
+            let in_root = self
+                .root
+                .as_ref()
+                .is_some_and(|root| module_path(root, &path).is_some());
+            match fs::read_to_string(&path) {
+                Ok(text) if in_root && is_script(&path) => {
+                    self.files.insert(url.clone(), text);
+                }
+                _ => {
+                    self.files.remove(url);
+                }
+            }
+        }
+        self.load();
+    }
+
+    pub fn documents(&self) -> impl Iterator<Item = &Url> {
+        self.documents.keys()
+    }
+
+    pub fn module_id(&self, url: &Url) -> Option<ModuleID> {
+        self.urls
+            .iter()
+            .find_map(|(module_id, module_url)| (module_url == url).then_some(*module_id))
+    }
+
+    pub fn url(&self, module_id: ModuleID) -> Option<&Url> {
+        self.urls.get(&module_id)
+    }
+
+    pub fn syntax(&self, module_id: ModuleID) -> Option<SyntaxNode> {
+        Some(SyntaxNode::new_root(self.syntax.get(&module_id)?.clone()))
+    }
+
+    /// The diagnostics of an open document, empty if it isn't one.
+    pub fn diagnostics(&self, url: &Url) -> Vec<Diagnostic> {
+        if let Some(diagnostic) = self.unloaded.get(url) {
+            return vec![diagnostic.clone()];
+        }
+        let Some(module_id) = self.module_id(url) else {
+            return Vec::new();
+        };
+        self.index
+            .reports(module_id)
+            .map(|report| self.diagnostic(module_id, report))
+            .collect()
+    }
+
+    fn diagnostic(&self, module_id: ModuleID, report: &Report) -> Diagnostic {
+        let span = report.span();
+        let range = if span.0 == module_id {
+            self.range(&span).unwrap_or_default()
+        } else {
+            Range::default()
+        };
+
+        let mut message = report.message.clone().unwrap_or_default();
+        for extra in [&report.note, &report.help].into_iter().flatten() {
+            message.push('\n');
+            message.push_str(extra);
+        }
+
+        let related_information = report
+            .labels
+            .iter()
+            .filter_map(|label| {
+                Some(DiagnosticRelatedInformation {
+                    location: self.location(&label.span)?,
+                    message: label.message.clone()?,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Diagnostic {
+            range,
+            severity: Some(match report.kind {
+                ReportKind::Warning => DiagnosticSeverity::WARNING,
+                ReportKind::Advice => DiagnosticSeverity::INFORMATION,
+                ReportKind::Error | ReportKind::Custom(..) => DiagnosticSeverity::ERROR,
+            }),
+            code: Some(NumberOrString::String(report.code.as_str().to_string())),
+            source: Some("stork".to_string()),
+            message,
+            related_information: (!related_information.is_empty()).then_some(related_information),
+            ..Default::default()
+        }
+    }
+
+    pub fn location(&self, span: &Span) -> Option<Location> {
+        Some(Location {
+            uri: self.url(span.0)?.clone(),
+            range: self.range(span)?,
+        })
+    }
+
+    /// `None` for modules without source, like `std`.
+    pub fn range(&self, span: &Span) -> Option<Range> {
+        self.url(span.0)?;
+        let text = self.index.modules.get_ref(span.0).source.text();
+        Some(Range {
+            start: position(text, span.1.start),
+            end: position(text, span.1.end),
+        })
+    }
+
+    /// Loads the `.strk` files in `dir` and its subdirectories, except hidden ones.
+    fn load_dir(&mut self, dir: &Path) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                if !entry.file_name().to_string_lossy().starts_with('.') {
+                    self.load_dir(&path);
+                }
+                continue;
+            }
+            if !is_script(&path) {
+                continue;
+            }
+            let (Ok(url), Ok(text)) = (Url::from_file_path(&path), fs::read_to_string(&path))
+            else {
+                continue;
+            };
+            self.files.insert(url, text);
+        }
+    }
+
+    /// The module `url` is loaded as.
+    fn module_path(&self, url: &Url) -> Option<String> {
+        if let (Some(root), Ok(file)) = (&self.root, url.to_file_path()) {
+            if let Some(path) = module_path(root, &file) {
+                return Some(path);
+            }
+        }
+        Some(Path::new(url.path()).file_stem()?.to_str()?.to_string())
+    }
+
+    /// Parses every module again, for when the modules themselves change.
+    fn load(&mut self) {
+        // Open documents take precedence over what's on disk
+        let texts: HashMap<_, _> = self.files.iter().chain(&self.documents).collect();
+        let mut sources: BTreeMap<String, Vec<(&Url, &String)>> = BTreeMap::new();
+        for (url, text) in texts {
+            if let Some(path) = self.module_path(url) {
+                sources.entry(path).or_default().push((url, text));
+            }
+        }
+
+        self.index = ModuleIndex::default();
+        self.urls.clear();
+        self.syntax.clear();
+        self.unloaded.clear();
+        for (path, mut sources) in sources {
+            sources.sort_by_key(|(url, _)| *url);
+            let (url, text) = sources[0];
+            // Every other document with the same path gets an error instead
+            for (other, _) in &sources[1..] {
+                self.unloaded.insert(
+                    (*other).clone(),
+                    unloaded(format!("`{path}` is already the module in {url}")),
+                );
+            }
+            if path == "std" {
+                self.unloaded.insert(
+                    url.clone(),
+                    unloaded("`std` is builtin, rename this file".to_string()),
+                );
+                continue;
+            }
+            let module_id = self.index.modules.all_ids().count();
+            let (module, green) = match Module::parse(text, module_id) {
+                Ok(parsed) => parsed,
+                Err(report) => {
+                    // There's no module to take the range from
+                    let mut diagnostic = self.diagnostic(module_id, &report);
+                    let span = report.span().1;
+                    diagnostic.range =
+                        Range::new(position(text, span.start), position(text, span.end));
+                    if report.message.is_none() {
+                        diagnostic.message.insert_str(0, "Couldn't parse this file");
+                    }
+                    self.unloaded.insert(url.clone(), diagnostic);
+                    continue;
+                }
+            };
+            self.index.add_module(path, |_| Ok(module)).unwrap();
+            self.urls.insert(module_id, url.clone());
+            self.syntax.insert(module_id, green);
+        }
+        self.index
+            .add_module("std", |_| Ok(self.schema.to_module()))
+            .unwrap();
+        self.compile();
+    }
+
+    fn compile(&mut self) {
+        if let Err(err) = self.index.compile() {
+            eprintln!("Couldn't compile: {err:#}");
+        }
+    }
+}
+
+/// The `std` of a game without any of its own types, as `stork schema` exports it.
+pub fn basic_std() -> Schema {
+    Schema::parse(include_str!("../std.schema.json")).unwrap()
+}
+
+fn is_script(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "strk")
+}
+
+fn unloaded(message: String) -> Diagnostic {
+    Diagnostic {
+        severity: Some(DiagnosticSeverity::ERROR),
+        source: Some("stork".to_string()),
+        message,
+        ..Default::default()
+    }
+}
+
+/// Converts a byte offset into an LSP position, which counts UTF-16 code units.
+pub fn position(text: &str, offset: usize) -> Position {
+    let offset = offset.min(text.len());
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].encode_utf16().count() as u32,
+    }
+}
+
+/// Converts an LSP position into a byte offset, clamped to the line and the text.
+pub fn offset(text: &str, position: Position) -> usize {
+    let line_start = text
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(str::len)
+        .sum::<usize>();
+    let line = text[line_start..].split('\n').next().unwrap_or_default();
+
+    let mut character = 0;
+    for (offset, char) in line.char_indices() {
+        if character >= position.character as usize {
+            return line_start + offset;
+        }
+        character += char.len_utf16();
+    }
+    line_start + line.len()
+}
+
+mod code_actions;
+mod completion;
+mod hover;
+mod inlay_hints;
+mod navigation;
+mod rename;
+pub mod semantic_tokens;
+mod signature_help;
+mod structure;
+
+#[cfg(test)]
+mod test;
@@ -1,5 +1,6 @@
 import { ExtensionContext } from "vscode";
 import * as vscode from "vscode";
+import * as path from "path";
 
 import {
   createServerSocketTransport,
@@ -37,10 +38,19 @@ export function restart(): Thenable<void> | undefined {
 }
 
 function start() {
-  let serverOptions: ServerOptions = async () => {
-    let [reader, writer] = createServerSocketTransport(50022, "utf-8");
-    return { reader, writer };
-  };
+  let config = vscode.workspace.getConfiguration("stork.server");
+  let port = config.get<number | null>("port", null);
+
+  // Either attach to a server started with `--port`, or launch one over stdio
+  let serverOptions: ServerOptions = port
+    ? async () => {
+        let [reader, writer] = createServerSocketTransport(port, "utf-8");
+        return { reader, writer };
+      }
+    : {
+        command: config.get<string>("path", "stork-script-lsp"),
+        args: ["--stdio", ...schemaArgs(config.get<string | null>("schema", null))],
+      };
 
   let clientOptions: LanguageClientOptions = {
     documentSelector: [{ scheme: "file", language: "stork" }],
@@ -55,3 +65,15 @@ function start() {
 
   client.start();
 }
+
+/** Passes the game's exported schema, relative paths are from the first workspace folder. */
+function schemaArgs(schema: string | null): string[] {
+  if (!schema) {
+    return [];
+  }
+  let folder = vscode.workspace.workspaceFolders?.[0];
+  if (path.isAbsolute(schema) || !folder) {
+    return ["--schema", schema];
+  }
+  return ["--schema", vscode.Uri.joinPath(folder.uri, schema).fsPath];
+}